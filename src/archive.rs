@@ -0,0 +1,141 @@
+//! Reading game files straight out of compressed files and archives.
+//!
+//! Plain files, gzipped files (`.sgf.gz`), zip archives and tarballs (optionally gzipped) are
+//! supported. Nothing is extracted to disk; members are decompressed into memory one at a time.
+
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use flate2::read::GzDecoder;
+
+use crate::formats::is_game_file;
+
+/// A game file read from disk, along with its name within the archive it came from, if any.
+pub struct Member {
+    pub name: Option<String>,
+    pub bytes: Vec<u8>,
+}
+
+enum Kind {
+    Plain,
+    Gzip,
+    Zip,
+    Tar,
+    TarGz,
+}
+
+impl Kind {
+    fn from_path(path: &Path) -> Kind {
+        let file_name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        if file_name.ends_with(".zip") {
+            Kind::Zip
+        } else if file_name.ends_with(".tar") {
+            Kind::Tar
+        } else if file_name.ends_with(".tar.gz") || file_name.ends_with(".tgz") {
+            Kind::TarGz
+        } else if file_name.ends_with(".gz") {
+            Kind::Gzip
+        } else {
+            Kind::Plain
+        }
+    }
+}
+
+/// Reads every game file in `path`, passing each to `f` as soon as it's read, so only one member
+/// is held in memory at a time. Plain and gzipped files are a single unnamed member.
+pub fn for_each_member(path: &Path, mut f: impl FnMut(Member)) -> std::io::Result<()> {
+    match Kind::from_path(path) {
+        Kind::Plain | Kind::Gzip => f(Member {
+            name: None,
+            bytes: read_member(path, None)?,
+        }),
+        Kind::Zip => {
+            let mut archive = zip::ZipArchive::new(File::open(path)?)?;
+            for i in 0..archive.len() {
+                let mut file = archive.by_index(i)?;
+                if !file.is_file() || !is_game_file(file.name()) {
+                    continue;
+                }
+                // Members are compressed separately, so one bad member needn't lose the rest.
+                let mut bytes = vec![];
+                if let Err(error) = file.read_to_end(&mut bytes) {
+                    eprintln!(
+                        "Error reading {}/{}: {}",
+                        path.to_string_lossy(),
+                        file.name(),
+                        error
+                    );
+                    continue;
+                }
+                f(Member {
+                    name: Some(file.name().to_string()),
+                    bytes,
+                });
+            }
+        }
+        Kind::Tar => for_each_tar_member(File::open(path)?, f)?,
+        Kind::TarGz => for_each_tar_member(GzDecoder::new(File::open(path)?), f)?,
+    }
+
+    Ok(())
+}
+
+/// Reads a single member of the archive at `path`, or the whole file if `name` is `None`.
+pub fn read_member(path: &Path, name: Option<&str>) -> std::io::Result<Vec<u8>> {
+    let mut bytes = vec![];
+    match (Kind::from_path(path), name) {
+        (Kind::Plain, None) => bytes = std::fs::read(path)?,
+        (Kind::Gzip, None) => {
+            GzDecoder::new(File::open(path)?).read_to_end(&mut bytes)?;
+        }
+        (Kind::Zip, Some(name)) => {
+            let mut archive = zip::ZipArchive::new(File::open(path)?)?;
+            archive.by_name(name)?.read_to_end(&mut bytes)?;
+        }
+        (Kind::Tar, Some(name)) => bytes = read_tar_member(File::open(path)?, name)?,
+        (Kind::TarGz, Some(name)) => {
+            bytes = read_tar_member(GzDecoder::new(File::open(path)?), name)?
+        }
+        _ => return Err(std::io::ErrorKind::NotFound.into()),
+    }
+
+    Ok(bytes)
+}
+
+fn for_each_tar_member<R: Read>(reader: R, mut f: impl FnMut(Member)) -> std::io::Result<()> {
+    let mut archive = tar::Archive::new(reader);
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let name = entry.path()?.to_string_lossy().into_owned();
+        if !entry.header().entry_type().is_file() || !is_game_file(&name) {
+            continue;
+        }
+        let mut bytes = vec![];
+        entry.read_to_end(&mut bytes)?;
+        f(Member {
+            name: Some(name),
+            bytes,
+        });
+    }
+
+    Ok(())
+}
+
+// Tarballs have no index, so this reads through the archive until it finds the member.
+fn read_tar_member<R: Read>(reader: R, name: &str) -> std::io::Result<Vec<u8>> {
+    let mut archive = tar::Archive::new(reader);
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if entry.path()?.to_string_lossy() == name {
+            let mut bytes = vec![];
+            entry.read_to_end(&mut bytes)?;
+            return Ok(bytes);
+        }
+    }
+
+    Err(std::io::ErrorKind::NotFound.into())
+}
@@ -2,25 +2,38 @@ extern crate getopts;
 
 use std::path::PathBuf;
 
+use super::scan::{ScanOptions, DEFAULT_INCLUDE};
+use super::ui::{GameOrder, VariationPolicy};
+use super::xdg;
 use super::xscreensaver_context::WindowType;
 
 const DEFAULT_MOVE_DELAY: u64 = 5000;
 const DEFAULT_END_DELAY: u64 = 10000;
+const DEFAULT_AVOID_RECENT: usize = 20;
 
 #[derive(Debug)]
 pub struct GobanHackArgs {
     pub window_type: WindowType,
     pub sgf_dirs: Vec<PathBuf>,
+    pub scan_options: ScanOptions,
     pub move_delay: u64,
     pub end_delay: u64,
+    pub reverse: bool,
+    pub show_influence: bool,
+    pub variation_policy: VariationPolicy,
+    pub game_order: GameOrder,
+    pub seed: Option<u64>,
+    pub avoid_recent: usize,
     pub print_help: bool,
 }
 
 #[derive(Debug)]
+#[allow(clippy::enum_variant_names)]
 pub enum UsageError {
     ArgumentParseError,
     TooManyInputsError,
     FlagParseError,
+    ConflictingFlagsError,
 }
 
 impl std::fmt::Display for UsageError {
@@ -29,6 +42,7 @@ impl std::fmt::Display for UsageError {
             UsageError::ArgumentParseError => write!(f, "Failed to parse arguments."),
             UsageError::TooManyInputsError => write!(f, "Too many inputs."),
             UsageError::FlagParseError => write!(f, "Failed to parse flag."),
+            UsageError::ConflictingFlagsError => write!(f, "Conflicting flags."),
         }
     }
 }
@@ -46,15 +60,39 @@ pub fn parse_args(opts: &getopts::Options, args: &[String]) -> Result<GobanHackA
 
     let window_type = parse_window_type(&matches)?;
     let sgf_dirs = parse_sgf_dirs(&matches);
+    let scan_options = parse_scan_options(&matches)?;
     let move_delay = parse_flag_or_default(&matches, "move-delay", DEFAULT_MOVE_DELAY)?;
     let end_delay = parse_flag_or_default(&matches, "end-delay", DEFAULT_END_DELAY)?;
+    let reverse = matches.opt_present("reverse");
+    let show_influence = matches.opt_present("influence");
+    let mut variation_policy = parse_variation_policy(&matches)?;
+    // Playing backwards starts from the end of a single line, so there's nowhere to branch from.
+    // Both can be picked in xscreensaver's settings though, so don't refuse to start.
+    if reverse && variation_policy == VariationPolicy::All {
+        eprintln!("Variations can't be played in reverse. Playing the main line only.");
+        variation_policy = VariationPolicy::MainLine;
+    }
+    let game_order = parse_game_order(&matches)?;
+    let seed = matches
+        .opt_str("seed")
+        .map(|s| s.parse::<u64>())
+        .transpose()
+        .map_err(|_| UsageError::FlagParseError)?;
+    let avoid_recent = parse_flag_or_default(&matches, "avoid-recent", DEFAULT_AVOID_RECENT)?;
     let print_help = matches.opt_present("h");
 
     Ok(GobanHackArgs {
         window_type,
         sgf_dirs,
+        scan_options,
         move_delay,
         end_delay,
+        reverse,
+        show_influence,
+        variation_policy,
+        game_order,
+        seed,
+        avoid_recent,
         print_help,
     })
 }
@@ -90,12 +128,83 @@ pub fn build_opts() -> getopts::Options {
         ),
         "NUM",
     );
+    opts.optflag(
+        "",
+        "reverse",
+        "Play games backwards from the final position",
+    );
+    opts.optflag("", "influence", "Shade the area each player controls");
+    opts.optflag("", "variations", "Play every variation after the main line");
+    opts.optflag(
+        "",
+        "random-variations",
+        "Pick a random variation at every branch",
+    );
+    opts.optflag(
+        "",
+        "longest-variation",
+        "Play the longest line of each game",
+    );
+    opts.optopt(
+        "",
+        "variation-path",
+        "Variation to pick at each branch (e.g. 0-2-1), overriding other variation flags",
+        "PATH",
+    );
+    opts.optflag(
+        "",
+        "marked-variation",
+        "Follow variations marked as the main line",
+    );
+    opts.optopt(
+        "",
+        "order",
+        "Order to play games in: random, shuffle or sequential (default random)",
+        "ORDER",
+    );
+    opts.optopt(
+        "",
+        "seed",
+        "Seed for random choices, to replay a run exactly",
+        "NUM",
+    );
+    opts.optopt(
+        "",
+        "avoid-recent",
+        &format!(
+            "Number of recently shown games to avoid, even across runs (default {})",
+            DEFAULT_AVOID_RECENT
+        ),
+        "NUM",
+    );
     opts.optmulti(
         "",
         "sgf-dir",
         "Directory to search for sgf files. Multiple allowed.",
         "DIR",
     );
+    opts.optflag("", "recursive", "Search sgf directories recursively");
+    opts.optopt(
+        "",
+        "max-depth",
+        "Maximum depth of subdirectories to search (implies -recursive)",
+        "NUM",
+    );
+    opts.optmulti(
+        "",
+        "include",
+        &format!(
+            "Glob for files to load. Multiple allowed. (default {})",
+            DEFAULT_INCLUDE.join(" ")
+        ),
+        "GLOB",
+    );
+    opts.optmulti(
+        "",
+        "exclude",
+        "Glob for files or directories to skip. Multiple allowed.",
+        "GLOB",
+    );
 
     opts
 }
@@ -117,6 +226,76 @@ fn parse_flag_or_default<T: ::std::str::FromStr>(
         .map_err(|_| UsageError::FlagParseError)
 }
 
+pub fn parse_variation_policy(matches: &getopts::Matches) -> Result<VariationPolicy, UsageError> {
+    // The path is a separate field in xscreensaver's settings, next to the choice of the other
+    // policies, so filling it in overrides that choice rather than conflicting with it.
+    let path = matches
+        .opt_str("variation-path")
+        .filter(|path| !path.trim().is_empty());
+    if let Some(path) = path {
+        let path = path
+            .split('-')
+            .map(|index| index.trim().parse::<usize>())
+            .collect::<Result<_, _>>()
+            .map_err(|_| UsageError::FlagParseError)?;
+        return Ok(VariationPolicy::Path(path));
+    }
+    let mut policies = vec![];
+    if matches.opt_present("variations") {
+        policies.push(VariationPolicy::All);
+    }
+    if matches.opt_present("random-variations") {
+        policies.push(VariationPolicy::Random);
+    }
+    if matches.opt_present("longest-variation") {
+        policies.push(VariationPolicy::Longest);
+    }
+    if matches.opt_present("marked-variation") {
+        policies.push(VariationPolicy::Marked);
+    }
+    if policies.len() > 1 {
+        return Err(UsageError::ConflictingFlagsError);
+    }
+
+    Ok(policies.pop().unwrap_or(VariationPolicy::MainLine))
+}
+
+pub fn parse_game_order(matches: &getopts::Matches) -> Result<GameOrder, UsageError> {
+    match matches.opt_str("order").as_deref() {
+        None | Some("random") => Ok(GameOrder::Random),
+        Some("shuffle") => Ok(GameOrder::Shuffle),
+        Some("sequential") => Ok(GameOrder::Sequential),
+        Some(_) => Err(UsageError::FlagParseError),
+    }
+}
+
+pub fn parse_scan_options(matches: &getopts::Matches) -> Result<ScanOptions, UsageError> {
+    let max_depth = match matches.opt_str("max-depth") {
+        // Depth 1 is the top level of each directory, so add one to count subdirectories.
+        Some(depth) => {
+            depth
+                .parse::<usize>()
+                .map_err(|_| UsageError::FlagParseError)?
+                + 1
+        }
+        None if matches.opt_present("recursive") => usize::MAX,
+        None => 1,
+    };
+    let mut include = matches.opt_strs("include");
+    if include.is_empty() {
+        include = DEFAULT_INCLUDE
+            .iter()
+            .map(|glob| glob.to_string())
+            .collect();
+    }
+
+    Ok(ScanOptions {
+        max_depth,
+        include,
+        exclude: matches.opt_strs("exclude"),
+    })
+}
+
 pub fn parse_sgf_dirs(matches: &getopts::Matches) -> Vec<PathBuf> {
     let sgf_dirs: Vec<_> = matches
         .opt_strs("sgf-dir")
@@ -176,28 +355,51 @@ fn parse_window_id(window_id_string: &str) -> Result<u64, std::num::ParseIntErro
 }
 
 fn get_default_sgf_dir() -> Option<PathBuf> {
-    let xdg_data_home: PathBuf = {
-        match std::env::var("XDG_DATA_HOME").ok().map(PathBuf::from) {
-            Some(path) => path,
-            None => {
-                let mut path =
-                    PathBuf::from(std::env::var("HOME").unwrap_or_else(|_| "".to_string()));
-                path.push(".local/share");
-
-                path
-            }
-        }
-    };
+    let xdg_data_home = xdg::base_dir("XDG_DATA_HOME", ".local/share");
     let xdg_data_dirs: Vec<PathBuf> = std::env::var("XDG_DATA_DIRS")
         .unwrap_or_else(|_| "/usr/local/share:/usr/share".to_string())
         .split(':')
         .map(PathBuf::from)
         .collect();
     std::iter::once(xdg_data_home)
-        .chain(xdg_data_dirs.into_iter())
+        .chain(xdg_data_dirs)
         .map(|mut path| {
             path.push("goban-screenhack");
             path
         })
         .find(|path| path.exists())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(flags: &[&str]) -> Result<GobanHackArgs, UsageError> {
+        let args: Vec<_> = std::iter::once("goban")
+            .chain(flags.iter().copied())
+            .map(String::from)
+            .collect();
+        parse_args(&build_opts(), &args)
+    }
+
+    #[test]
+    fn reverse_plays_main_line_instead_of_variations() {
+        let args = parse(&["-reverse", "-variations"]).unwrap();
+        assert!(args.reverse);
+        assert_eq!(args.variation_policy, VariationPolicy::MainLine);
+    }
+
+    #[test]
+    fn variation_path_overrides_other_variation_flags() {
+        let args = parse(&["-random-variations", "-variation-path", "0-2-1"]).unwrap();
+        assert_eq!(args.variation_policy, VariationPolicy::Path(vec![0, 2, 1]));
+        let args = parse(&["-random-variations", "-variation-path", ""]).unwrap();
+        assert_eq!(args.variation_policy, VariationPolicy::Random);
+        assert!(parse(&["-variation-path", "0-x"]).is_err());
+    }
+
+    #[test]
+    fn rejects_conflicting_variation_flags() {
+        assert!(parse(&["-random-variations", "-longest-variation"]).is_err());
+    }
+}
@@ -0,0 +1,443 @@
+//! An index of the games in the sgf directories, cached on disk between runs.
+//!
+//! Indexing a file means parsing it once to find its games. After that only a game about to be
+//! shown needs to be read and parsed again, using the byte range stored in the catalog. Files are
+//! re-indexed whenever their modification time changes. Archives are indexed like any other file,
+//! with each game also recording the archive member it's in.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use sgf_parse::{go, SgfNode};
+
+use crate::archive;
+use crate::formats;
+use crate::large_boards;
+use crate::xdg;
+
+const CACHE_HEADER: &str = "goban-screenhack catalog 4";
+
+/// Where to find a single game, along with some information about it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CatalogEntry {
+    /// Identifies the game across runs: the file path (followed by the member name for archives),
+    /// numbered if the file has several games.
+    pub source: String,
+    pub path: PathBuf,
+    /// Name of the file within the archive at `path`, if it's an archive.
+    pub member: Option<String>,
+    /// Byte range of the game tree within the file's SGF text, which is what's left once the file
+    /// has been decompressed, decoded and converted to SGF.
+    pub offset: usize,
+    pub length: usize,
+    pub board_size: (u8, u8),
+    pub black_player: String,
+    pub white_player: String,
+}
+
+impl CatalogEntry {
+    /// Reads and parses the game.
+    pub fn load(&self) -> Result<SgfNode<go::Prop>, Box<dyn std::error::Error>> {
+        let bytes = archive::read_member(&self.path, self.member.as_deref())?;
+        let name = match &self.member {
+            Some(member) => member.clone(),
+            None => self.path.to_string_lossy().into_owned(),
+        };
+        let text = formats::to_sgf(&name, &bytes)?;
+        let text = text
+            .get(self.offset..self.offset + self.length)
+            .ok_or(CatalogError::StaleEntry)?;
+
+        large_boards::parse(text)?
+            .into_iter()
+            .next()
+            .ok_or_else(|| CatalogError::StaleEntry.into())
+    }
+}
+
+#[derive(Default)]
+pub struct Catalog {
+    files: HashMap<PathBuf, IndexedFile>,
+    // Files loaded from the cache which haven't been checked since.
+    cached_files: HashMap<PathBuf, IndexedFile>,
+}
+
+struct IndexedFile {
+    modified: (u64, u32),
+    entries: Vec<CatalogEntry>,
+}
+
+impl Catalog {
+    /// Loads the catalog cached at `path`, or an empty one if there's no usable cache.
+    pub fn load(path: &Path) -> Catalog {
+        match std::fs::read_to_string(path) {
+            Ok(contents) => parse_cache(&contents).unwrap_or_default(),
+            Err(_) => Catalog::default(),
+        }
+    }
+
+    /// Returns the path of the cache file under `$XDG_CACHE_HOME`.
+    pub fn default_path() -> PathBuf {
+        let mut path = xdg::base_dir("XDG_CACHE_HOME", ".cache");
+        path.push("goban-screenhack");
+        path.push("catalog");
+
+        path
+    }
+
+    /// Returns the games in a file, indexing it if it's new or modified since it was cached.
+    pub fn add_file(&mut self, path: &Path) -> Vec<CatalogEntry> {
+        let modified = match modified_time(path) {
+            Ok(modified) => modified,
+            Err(error) => {
+                eprintln!("Error reading {}: {}", path.to_string_lossy(), error);
+                return vec![];
+            }
+        };
+        let indexed_file = match self.cached_files.remove(path) {
+            Some(indexed_file) if indexed_file.modified == modified => indexed_file,
+            _ => IndexedFile {
+                modified,
+                entries: index_file(path),
+            },
+        };
+        let entries = indexed_file.entries.clone();
+        self.files.insert(path.to_path_buf(), indexed_file);
+
+        entries
+    }
+
+    /// Forgets every cached file which hasn't been added since the catalog was loaded. Called once
+    /// a scan is finished, so files which have gone away are dropped.
+    pub fn forget_missing(&mut self) {
+        self.cached_files.clear();
+    }
+
+    /// Writes the catalog to `path`, replacing the file atomically in case another instance is
+    /// reading it.
+    pub fn save(&self, path: &Path) -> std::io::Result<()> {
+        let mut contents = format!("{}\n", CACHE_HEADER);
+        for (file_path, indexed_file) in self.files.iter().chain(self.cached_files.iter()) {
+            let file_path = match file_path.to_str() {
+                Some(file_path) if !has_separator(file_path) => file_path,
+                _ => continue, // Not worth escaping. The file just gets indexed every time.
+            };
+            let members = indexed_file
+                .entries
+                .iter()
+                .filter_map(|entry| entry.member.as_ref());
+            if members.clone().any(|member| has_separator(member)) {
+                continue;
+            }
+            let (seconds, nanos) = indexed_file.modified;
+            contents.push_str(&format!("F\t{}\t{}\t{}\n", seconds, nanos, file_path));
+            for entry in &indexed_file.entries {
+                contents.push_str(&format!(
+                    "G\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\n",
+                    entry.source,
+                    entry.member.as_deref().unwrap_or(""),
+                    entry.offset,
+                    entry.length,
+                    entry.board_size.0,
+                    entry.board_size.1,
+                    entry.black_player,
+                    entry.white_player,
+                ));
+            }
+        }
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let temp_path = path.with_extension(format!("{}.tmp", std::process::id()));
+        std::fs::write(&temp_path, contents)?;
+        std::fs::rename(&temp_path, path)
+    }
+}
+
+fn parse_cache(contents: &str) -> Option<Catalog> {
+    let mut lines = contents.lines();
+    if lines.next()? != CACHE_HEADER {
+        return None;
+    }
+    let mut files: HashMap<PathBuf, IndexedFile> = HashMap::new();
+    let mut current_path = None;
+    for line in lines {
+        let fields: Vec<_> = line.split('\t').collect();
+        match fields[..] {
+            ["F", seconds, nanos, path] => {
+                let path = PathBuf::from(path);
+                let indexed_file = IndexedFile {
+                    modified: (seconds.parse().ok()?, nanos.parse().ok()?),
+                    entries: vec![],
+                };
+                files.insert(path.clone(), indexed_file);
+                current_path = Some(path);
+            }
+            ["G", source, member, offset, length, width, height, black_player, white_player] => {
+                let path = current_path.as_ref()?;
+                files.get_mut(path)?.entries.push(CatalogEntry {
+                    source: source.to_string(),
+                    path: path.clone(),
+                    member: Some(member.to_string()).filter(|member| !member.is_empty()),
+                    offset: offset.parse().ok()?,
+                    length: length.parse().ok()?,
+                    board_size: (width.parse().ok()?, height.parse().ok()?),
+                    black_player: black_player.to_string(),
+                    white_player: white_player.to_string(),
+                });
+            }
+            _ => return None,
+        }
+    }
+
+    Some(Catalog {
+        files: HashMap::new(),
+        cached_files: files,
+    })
+}
+
+fn modified_time(path: &Path) -> std::io::Result<(u64, u32)> {
+    let modified = std::fs::metadata(path)?.modified()?;
+    let duration = modified.duration_since(UNIX_EPOCH).unwrap_or_default();
+
+    Ok((duration.as_secs(), duration.subsec_nanos()))
+}
+
+// Finds the games in a file or archive, skipping any which fail to parse.
+fn index_file(path: &Path) -> Vec<CatalogEntry> {
+    let mut entries = vec![];
+    let result = archive::for_each_member(path, |member| {
+        let name = match &member.name {
+            Some(name) => format!("{}/{}", path.to_string_lossy(), name),
+            None => path.to_string_lossy().into_owned(),
+        };
+        match formats::to_sgf(&name, &member.bytes) {
+            Ok(text) => index_games(path, member.name, &name, &text, &mut entries),
+            Err(error) => eprintln!("Error converting {}: {}", name, error),
+        }
+    });
+    // Games found before an error in the middle of an archive are still worth keeping.
+    if let Err(error) = result {
+        eprintln!("Error reading {}: {}", path.to_string_lossy(), error);
+    }
+
+    entries
+}
+
+fn index_games(
+    path: &Path,
+    member: Option<String>,
+    name: &str,
+    text: &str,
+    entries: &mut Vec<CatalogEntry>,
+) {
+    let ranges = gametree_ranges(text);
+    for (i, &(offset, length)) in ranges.iter().enumerate() {
+        let root = match large_boards::parse(&text[offset..offset + length]) {
+            Ok(gametrees) if gametrees.len() == 1 => gametrees.into_iter().next().unwrap(),
+            Ok(_) => continue,
+            Err(error) => {
+                eprintln!("Error parsing {}: {}", name, error);
+                continue;
+            }
+        };
+        let source = if ranges.len() == 1 {
+            name.to_string()
+        } else {
+            format!("{}#{}", name, i)
+        };
+        entries.push(CatalogEntry {
+            source: sanitize(&source),
+            path: path.to_path_buf(),
+            member: member.clone(),
+            offset,
+            length,
+            board_size: match root.get_property("SZ") {
+                Some(go::Prop::SZ(size)) => *size,
+                _ => (19, 19),
+            },
+            black_player: player_name(&root, "PB"),
+            white_player: player_name(&root, "PW"),
+        });
+    }
+}
+
+fn player_name(root: &SgfNode<go::Prop>, identifier: &str) -> String {
+    match root.get_property(identifier) {
+        Some(go::Prop::PB(name)) | Some(go::Prop::PW(name)) => sanitize(&name.text),
+        _ => String::new(),
+    }
+}
+
+fn has_separator(text: &str) -> bool {
+    text.contains(&['\t', '\n'][..])
+}
+
+// Keeps text from breaking the tab separated cache format.
+fn sanitize(text: &str) -> String {
+    text.replace(&['\t', '\n', '\r'][..], " ")
+}
+
+// Returns the byte offset and length of each top level game tree in a collection.
+fn gametree_ranges(text: &str) -> Vec<(usize, usize)> {
+    let mut ranges = vec![];
+    let mut depth = 0;
+    let mut start = 0;
+    let mut in_value = false;
+    let mut escaped = false;
+    for (i, byte) in text.bytes().enumerate() {
+        if in_value {
+            match byte {
+                _ if escaped => escaped = false,
+                b'\\' => escaped = true,
+                b']' => in_value = false,
+                _ => {}
+            }
+            continue;
+        }
+        match byte {
+            b'[' => in_value = true,
+            b'(' => {
+                if depth == 0 {
+                    start = i;
+                }
+                depth += 1;
+            }
+            b')' if depth > 0 => {
+                depth -= 1;
+                if depth == 0 {
+                    ranges.push((start, i + 1 - start));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    ranges
+}
+
+#[derive(Debug)]
+pub enum CatalogError {
+    StaleEntry,
+}
+
+impl std::fmt::Display for CatalogError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            CatalogError::StaleEntry => write!(f, "Game is no longer where the catalog says."),
+        }
+    }
+}
+
+impl std::error::Error for CatalogError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    const COLLECTION: &str = "(;SZ[13]PB[Black (4d\\]]PW[White\tplayer]C[a [(b)]\n;B[aa])\n\
+                              (;SZ[9];B[cc](;W[dd])(;W[ee]))";
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("goban-screenhack-{}-{}", name, std::process::id()))
+    }
+
+    fn set_modified(path: &Path, seconds: u64) {
+        let file = std::fs::File::options().write(true).open(path).unwrap();
+        file.set_modified(UNIX_EPOCH + Duration::from_secs(seconds))
+            .unwrap();
+    }
+
+    #[test]
+    fn gametree_ranges_skip_property_values() {
+        let ranges = gametree_ranges(COLLECTION);
+        let games: Vec<_> = ranges
+            .iter()
+            .map(|&(offset, length)| &COLLECTION[offset..offset + length])
+            .collect();
+        assert_eq!(
+            games,
+            [
+                "(;SZ[13]PB[Black (4d\\]]PW[White\tplayer]C[a [(b)]\n;B[aa])",
+                "(;SZ[9];B[cc](;W[dd])(;W[ee]))",
+            ]
+        );
+    }
+
+    #[test]
+    fn gametree_ranges_ignore_text_between_games() {
+        let text = "header ) text (;B[aa]) more ( text";
+        assert_eq!(gametree_ranges(text), [(14, 8)]);
+    }
+
+    #[test]
+    fn indexes_each_game_with_metadata() {
+        let path = temp_path("index.sgf");
+        std::fs::write(&path, COLLECTION).unwrap();
+        let entries = Catalog::default().add_file(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        let name = path.to_string_lossy();
+        let summary: Vec<_> = entries
+            .iter()
+            .map(|entry| {
+                (
+                    entry.source.as_str(),
+                    entry.board_size,
+                    entry.black_player.as_str(),
+                    entry.white_player.as_str(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                (
+                    &*format!("{}#0", name),
+                    (13, 13),
+                    "Black (4d]",
+                    "White player"
+                ),
+                (&*format!("{}#1", name), (9, 9), "", ""),
+            ]
+        );
+    }
+
+    #[test]
+    fn cache_round_trip() {
+        let path = temp_path("cached.sgf");
+        let cache_path = temp_path("catalog");
+        std::fs::write(&path, COLLECTION).unwrap();
+        set_modified(&path, 1_000_000);
+        let mut catalog = Catalog::default();
+        let entries = catalog.add_file(&path);
+        catalog.save(&cache_path).unwrap();
+
+        // With the same modification time the cached entries are used, even though the file has
+        // changed underneath them.
+        std::fs::write(&path, "(;SZ[19]PB[Someone else])").unwrap();
+        set_modified(&path, 1_000_000);
+        let mut catalog = Catalog::load(&cache_path);
+        assert_eq!(catalog.add_file(&path), entries);
+
+        // Once it's modified the file is indexed again.
+        catalog.save(&cache_path).unwrap();
+        set_modified(&path, 2_000_000);
+        let mut catalog = Catalog::load(&cache_path);
+        let reindexed = catalog.add_file(&path);
+
+        std::fs::remove_file(&path).unwrap();
+        std::fs::remove_file(&cache_path).unwrap();
+        assert_eq!(reindexed.len(), 1);
+        assert_eq!(reindexed[0].board_size, (19, 19));
+        assert_eq!(reindexed[0].black_player, "Someone else");
+    }
+
+    #[test]
+    fn stale_cache_versions_are_ignored() {
+        let contents = "goban-screenhack catalog 3\nF\t/some/file.sgf\t1\t0\n";
+        assert!(parse_cache(contents).is_none());
+    }
+}
@@ -0,0 +1,92 @@
+//! Decoding game files to text.
+//!
+//! SGF files name their character set with the `CA` property. Files which don't, and other
+//! formats which have no way to say, are read as UTF-8 if they're valid UTF-8, and otherwise
+//! decoded with whatever encoding looks most likely.
+
+use chardetng::EncodingDetector;
+use encoding_rs::Encoding;
+
+/// Decodes a file, using the `CA` property if it has one. `region` is the top level domain of
+/// the region the file probably comes from, to help guess the encoding.
+pub fn decode(bytes: &[u8], region: Option<&[u8]>) -> String {
+    let encoding = match declared_charset(bytes).and_then(Encoding::for_label) {
+        Some(encoding) => encoding,
+        None => match std::str::from_utf8(bytes) {
+            Ok(text) => return text.to_string(),
+            Err(_) => {
+                let mut detector = EncodingDetector::new();
+                detector.feed(bytes, true);
+                detector.guess(region, false)
+            }
+        },
+    };
+
+    encoding.decode_with_bom_removal(bytes).0.into_owned()
+}
+
+// Finds the value of the first `CA` property. Charset names are ASCII, and the property is
+// normally near the start of the file, before any text in the charset itself.
+fn declared_charset(bytes: &[u8]) -> Option<&[u8]> {
+    let start = (0..bytes.len()).find(|&i| {
+        bytes[i..].starts_with(b"CA[") && (i == 0 || !bytes[i - 1].is_ascii_uppercase())
+    })? + 3;
+    let length = bytes[start..].iter().position(|&b| b == b']')?;
+
+    Some(&bytes[start..start + length])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use encoding_rs::{EUC_KR, SHIFT_JIS, WINDOWS_1252};
+
+    fn encode(encoding: &'static Encoding, text: &str) -> Vec<u8> {
+        let (bytes, _, unmappable) = encoding.encode(text);
+        assert!(!unmappable);
+        bytes.into_owned()
+    }
+
+    #[test]
+    fn declared_charset_finds_ca() {
+        assert_eq!(
+            declared_charset(b"(;GM[1]CA[UTF-8]SZ[19])"),
+            Some(&b"UTF-8"[..])
+        );
+        assert_eq!(declared_charset(b"CA[Shift_JIS]"), Some(&b"Shift_JIS"[..]));
+        assert_eq!(declared_charset(b"(;GM[1]SZ[19])"), None);
+        assert_eq!(declared_charset(b"(;CA[UTF-8"), None);
+    }
+
+    #[test]
+    fn declared_charset_skips_longer_property_names() {
+        assert_eq!(declared_charset(b"(;XCA[EUC-KR])"), None);
+        assert_eq!(
+            declared_charset(b"(;XCA[EUC-KR]CA[UTF-8])"),
+            Some(&b"UTF-8"[..])
+        );
+    }
+
+    #[test]
+    fn decodes_declared_charsets() {
+        let text = "(;CA[Shift_JIS]PB[本因坊秀策]PW[井上幻庵因碩])";
+        assert_eq!(decode(&encode(SHIFT_JIS, text), None), text);
+
+        let text = "(;CA[EUC-KR]PB[이창호]PW[조훈현]C[백 불계승])";
+        assert_eq!(decode(&encode(EUC_KR, text), None), text);
+    }
+
+    #[test]
+    fn detects_undeclared_charsets() {
+        let text = "(;PB[Jürgen Müller]PW[Françoise Lefèvre]C[Schöne Partie])";
+        assert_eq!(decode(&encode(WINDOWS_1252, text), None), text);
+        assert_eq!(decode(text.as_bytes(), None), text);
+    }
+
+    #[test]
+    fn ignores_ca_inside_longer_property_names() {
+        // Decoded as UTF-8 rather than as the charset named by `XCA`.
+        let text = "(;XCA[Shift_JIS]PB[Müller])";
+        assert_eq!(decode(text.as_bytes(), None), text);
+    }
+}
@@ -0,0 +1,206 @@
+//! Tygem's `.gib` game records.
+//!
+//! The header is a list of `\[KEY=VALUE\]` lines, and the moves follow one per line:
+//!
+//! ```text
+//! INI 0 1 <handicap> &4
+//! STO 0 <move number> <color> <x> <y>
+//! SKI 0 <move number>
+//! ```
+//!
+//! Colors are 1 for black and 2 for white, and coordinates count from 0 at the top left. `SKI`
+//! is a pass. Handicap stones aren't listed, since they always go on the usual points.
+
+use std::collections::HashMap;
+
+use super::{push_property, sgf_point};
+
+pub fn to_sgf(text: &str) -> Result<String, GibError> {
+    let mut header = HashMap::new();
+    let mut handicap = 0;
+    let mut moves = String::new();
+    let mut next_color = None;
+    for line in text.lines() {
+        let line = line.trim();
+        if let Some(field) = line.strip_prefix("\\[").and_then(|s| s.strip_suffix("\\]")) {
+            if let Some((key, value)) = field.split_once('=') {
+                header.insert(key.trim(), value.trim());
+            }
+            continue;
+        }
+        let fields: Vec<_> = line.split_whitespace().collect();
+        match fields[..] {
+            ["INI", _, _, count, ..] => handicap = count.parse().unwrap_or(0),
+            ["STO", _, _, color, x, y, ..] => {
+                let color = match color {
+                    "1" => 'B',
+                    "2" => 'W',
+                    _ => return Err(GibError::InvalidMove(line.to_string())),
+                };
+                let point = x
+                    .parse()
+                    .ok()
+                    .zip(y.parse().ok())
+                    .and_then(|(x, y)| sgf_point(x, y))
+                    .ok_or_else(|| GibError::InvalidMove(line.to_string()))?;
+                moves.push_str(&format!(";{}[{}]", color, point));
+                next_color = Some(if color == 'B' { 'W' } else { 'B' });
+            }
+            ["SKI", ..] => {
+                // Passes don't say whose they are.
+                let color = next_color.unwrap_or(if handicap > 1 { 'W' } else { 'B' });
+                moves.push_str(&format!(";{}[]", color));
+                next_color = Some(if color == 'B' { 'W' } else { 'B' });
+            }
+            _ => {}
+        }
+    }
+    if header.is_empty() && moves.is_empty() {
+        return Err(GibError::NoGame);
+    }
+
+    let mut sgf = "(;GM[1]FF[4]SZ[19]".to_string();
+    for (color, key) in [("B", "GAMEBLACKNAME"), ("W", "GAMEWHITENAME")] {
+        let name = header.get(key).copied().unwrap_or_default();
+        let (name, rank) = split_rank(name);
+        push_property(&mut sgf, &format!("P{}", color), name);
+        push_property(&mut sgf, &format!("{}R", color), &rank.to_lowercase());
+    }
+    // Game settings and the result are in a single `KEY:VALUE,KEY:VALUE` field.
+    let info: HashMap<_, _> = header
+        .get("GAMEINFOMAIN")
+        .copied()
+        .unwrap_or_default()
+        .split(',')
+        .filter_map(|field| field.split_once(':'))
+        .collect();
+    if let Some(komi) = info.get("GONGJE").and_then(|komi| komi.parse::<i64>().ok()) {
+        push_property(&mut sgf, "KM", &format!("{}", komi as f64 / 10.0));
+    }
+    if handicap > 1 {
+        push_property(&mut sgf, "HA", &handicap.to_string());
+    }
+    push_property(&mut sgf, "RE", &result(&info));
+    push_property(&mut sgf, "DT", &date(header.get("GAMEDATE").copied()));
+    push_property(
+        &mut sgf,
+        "GN",
+        header.get("GAMENAME").copied().unwrap_or_default(),
+    );
+    push_property(
+        &mut sgf,
+        "PC",
+        header.get("GAMEPLACE").copied().unwrap_or_default(),
+    );
+    sgf.push_str(&moves);
+    sgf.push(')');
+
+    Ok(sgf)
+}
+
+// Player names usually include the rank, like `Lee Sedol (9D)`.
+fn split_rank(name: &str) -> (&str, &str) {
+    match name.strip_suffix(')').and_then(|s| s.rsplit_once('(')) {
+        Some((name, rank)) => (name.trim(), rank.trim()),
+        None => (name, ""),
+    }
+}
+
+// `GRLT` is the kind of win, and `ZIPSU` the margin in tenths of a point.
+fn result(info: &HashMap<&str, &str>) -> String {
+    let margin = info
+        .get("ZIPSU")
+        .and_then(|margin| margin.parse::<i64>().ok())
+        .map(|margin| format!("{}", margin as f64 / 10.0))
+        .unwrap_or_default();
+    match info.get("GRLT").copied() {
+        Some("0") => format!("B+{}", margin),
+        Some("1") => format!("W+{}", margin),
+        Some("3") => "B+R".to_string(),
+        Some("4") => "W+R".to_string(),
+        Some("7") => "B+T".to_string(),
+        Some("8") => "W+T".to_string(),
+        _ => String::new(),
+    }
+}
+
+// Dates look like `2016- 3- 9-14-05-31`, with the time after the date.
+fn date(value: Option<&str>) -> String {
+    let numbers: Vec<u32> = value
+        .unwrap_or_default()
+        .split(|c: char| !c.is_ascii_digit())
+        .filter(|s| !s.is_empty())
+        .filter_map(|s| s.parse().ok())
+        .collect();
+    match numbers[..] {
+        [year, month, day, ..] if year >= 1000 => format!("{}-{:02}-{:02}", year, month, day),
+        _ => String::new(),
+    }
+}
+
+#[derive(Debug)]
+pub enum GibError {
+    NoGame,
+    InvalidMove(String),
+}
+
+impl std::fmt::Display for GibError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            GibError::NoGame => write!(f, "No game record found."),
+            GibError::InvalidMove(line) => write!(f, "Invalid move: {}", line),
+        }
+    }
+}
+
+impl std::error::Error for GibError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GAME: &str = r"\[GAMEBLACKNAME=Lee Sedol (9D)\]
+\[GAMEWHITENAME=Gu Li (9D)\]
+\[GAMEINFOMAIN=GONGJE:65,GRLT:0,ZIPSU:25\]
+\[GAMEDATE=2016- 3- 9-14-05-31\]
+INI 0 1 0 &4
+STO 0 2 1 15 3
+STO 0 3 2 3 15
+SKI 0 4
+STO 0 5 2 16 16
+";
+
+    #[test]
+    fn converts_header_and_moves() {
+        assert_eq!(
+            to_sgf(GAME).unwrap(),
+            "(;GM[1]FF[4]SZ[19]PB[Lee Sedol]BR[9d]PW[Gu Li]WR[9d]KM[6.5]RE[B+2.5]DT[2016-03-09]\
+             ;B[pd];W[dp];B[];W[qq])"
+        );
+    }
+
+    #[test]
+    fn first_pass_in_handicap_game_is_white() {
+        let sgf = to_sgf("INI 0 1 3 &4\nSKI 0 1\nSTO 0 2 1 3 3\n").unwrap();
+        assert_eq!(sgf, "(;GM[1]FF[4]SZ[19]HA[3];W[];B[dd])");
+    }
+
+    #[test]
+    fn maps_results() {
+        let result_of =
+            |fields: &[(&'static str, &'static str)]| result(&fields.iter().copied().collect());
+        assert_eq!(result_of(&[("GRLT", "1"), ("ZIPSU", "5")]), "W+0.5");
+        assert_eq!(result_of(&[("GRLT", "3")]), "B+R");
+        assert_eq!(result_of(&[("GRLT", "8")]), "W+T");
+        assert_eq!(result_of(&[]), "");
+    }
+
+    #[test]
+    fn rejects_bad_moves() {
+        assert!(matches!(
+            to_sgf("STO 0 2 3 3 3\n"),
+            Err(GibError::InvalidMove(_))
+        ));
+        assert!(matches!(to_sgf("nothing here"), Err(GibError::NoGame)));
+    }
+}
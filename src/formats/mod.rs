@@ -0,0 +1,87 @@
+//! Game records in formats other than SGF.
+//!
+//! Records are decoded and converted to SGF text as they're read, so the catalog's byte ranges,
+//! parsing and everything after that only ever deal with SGF.
+
+mod charset;
+mod gib;
+mod ngf;
+mod ugf;
+
+use std::error::Error;
+
+enum Format {
+    Sgf,
+    Gib,
+    Ngf,
+    Ugf,
+}
+
+impl Format {
+    // Picks the format from the file extension, looking through any `.gz`.
+    fn from_name(name: &str) -> Option<Format> {
+        let name = name.to_lowercase();
+        let name = name.strip_suffix(".gz").unwrap_or(&name);
+        if name.ends_with(".sgf") {
+            Some(Format::Sgf)
+        } else if name.ends_with(".gib") {
+            Some(Format::Gib)
+        } else if name.ends_with(".ngf") {
+            Some(Format::Ngf)
+        } else if name.ends_with(".ugf") || name.ends_with(".ugi") {
+            Some(Format::Ugf)
+        } else {
+            None
+        }
+    }
+}
+
+/// Returns whether a file name has the extension of a supported game format.
+pub fn is_game_file(name: &str) -> bool {
+    Format::from_name(name).is_some()
+}
+
+/// Decodes the contents of the game file `name` and converts them to SGF. Files with an unknown
+/// extension are assumed to be SGF already.
+pub fn to_sgf(name: &str, bytes: &[u8]) -> Result<String, Box<dyn Error>> {
+    let format = Format::from_name(name);
+    // Formats without a charset property come from one country, so guess its encodings first.
+    let region: Option<&[u8]> = match format {
+        Some(Format::Gib) | Some(Format::Ngf) => Some(b"kr"),
+        Some(Format::Ugf) => Some(b"jp"),
+        Some(Format::Sgf) | None => None,
+    };
+    let text = charset::decode(bytes, region);
+    match format {
+        Some(Format::Sgf) | None => Ok(text),
+        Some(Format::Gib) => Ok(gib::to_sgf(&text)?),
+        Some(Format::Ngf) => Ok(ngf::to_sgf(&text)?),
+        Some(Format::Ugf) => Ok(ugf::to_sgf(&text)?),
+    }
+}
+
+// Appends a property to a node, unless the value is empty.
+fn push_property(sgf: &mut String, identifier: &str, value: &str) {
+    if value.is_empty() {
+        return;
+    }
+    sgf.push_str(identifier);
+    sgf.push('[');
+    for c in value.chars() {
+        if c == ']' || c == '\\' {
+            sgf.push('\\');
+        }
+        sgf.push(c);
+    }
+    sgf.push(']');
+}
+
+// Returns the SGF point for zero based coordinates, or `None` if they're off the board.
+fn sgf_point(x: usize, y: usize) -> Option<String> {
+    const LETTERS: &[u8] = b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ";
+    Some(format!(
+        "{}{}",
+        *LETTERS.get(x)? as char,
+        *LETTERS.get(y)? as char
+    ))
+}
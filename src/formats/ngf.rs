@@ -0,0 +1,200 @@
+//! WBaduk's `.ngf` game records.
+//!
+//! The first twelve lines are a fixed header:
+//!
+//! ```text
+//! <game name>
+//! <board size>
+//! <white player> <white rank>
+//! <black player> <black rank>
+//! <website>
+//! <handicap>
+//! <unused>
+//! <komi>
+//! <date, as YYYYMMDD> [<time>]
+//! <unused>
+//! <result, like "White wins by resignation">
+//! <number of moves>
+//! ```
+//!
+//! Each move after that is a line like `PMACBQDCA`: the move number, the color, and letter
+//! coordinates starting from `B`, with anything off the board being a pass.
+
+use super::{push_property, sgf_point};
+
+const HEADER_LINES: usize = 12;
+
+pub fn to_sgf(text: &str) -> Result<String, NgfError> {
+    let lines: Vec<_> = text.lines().map(str::trim).collect();
+    if lines.len() < HEADER_LINES {
+        return Err(NgfError::Truncated);
+    }
+    let size: usize = match lines[1].parse() {
+        Ok(size) if (1..=52).contains(&size) => size,
+        _ => return Err(NgfError::InvalidBoardSize(lines[1].to_string())),
+    };
+    let handicap: u32 = lines[5].parse().unwrap_or(0);
+
+    let mut sgf = format!("(;GM[1]FF[4]SZ[{}]", size);
+    for (color, line) in [("B", lines[3]), ("W", lines[2])] {
+        let (name, rank) = split_rank(line);
+        push_property(&mut sgf, &format!("P{}", color), name);
+        push_property(&mut sgf, &format!("{}R", color), &rank);
+    }
+    if let Ok(mut komi) = lines[7].parse::<f64>() {
+        // Komi is written without the half point in even games.
+        if handicap == 0 && komi.fract() == 0.0 {
+            komi += 0.5;
+        }
+        push_property(&mut sgf, "KM", &komi.to_string());
+    }
+    if handicap > 1 {
+        push_property(&mut sgf, "HA", &handicap.to_string());
+    }
+    push_property(&mut sgf, "RE", &result(lines[10]));
+    push_property(&mut sgf, "DT", &date(lines[8]));
+    push_property(&mut sgf, "GN", lines[0]);
+
+    for line in &lines[HEADER_LINES..] {
+        let line = line.as_bytes();
+        if !line.starts_with(b"PM") || line.len() < 7 {
+            continue;
+        }
+        let color = match line[4] {
+            b'B' => 'B',
+            b'W' => 'W',
+            _ => continue,
+        };
+        let x = line[5].wrapping_sub(b'B') as usize;
+        let y = line[6].wrapping_sub(b'B') as usize;
+        let point = match sgf_point(x, y) {
+            Some(point) if x < size && y < size => point,
+            _ => String::new(),
+        };
+        sgf.push_str(&format!(";{}[{}]", color, point));
+    }
+    sgf.push(')');
+
+    Ok(sgf)
+}
+
+// Ranks follow the name, like `Lee Changho 9D*`.
+fn split_rank(line: &str) -> (&str, String) {
+    match line.rsplit_once(char::is_whitespace) {
+        Some((name, rank)) if rank.starts_with(|c: char| c.is_ascii_digit()) => {
+            (name.trim(), rank.trim_end_matches('*').to_lowercase())
+        }
+        _ => (line, String::new()),
+    }
+}
+
+fn result(line: &str) -> String {
+    let line = line.to_lowercase();
+    let winner = if line.contains("white win") {
+        "W"
+    } else if line.contains("black win") {
+        "B"
+    } else {
+        return String::new();
+    };
+    let margin = if line.contains("resign") {
+        "R".to_string()
+    } else if line.contains("time") {
+        "T".to_string()
+    } else {
+        line.split_whitespace()
+            .find(|word| word.parse::<f64>().is_ok())
+            .unwrap_or_default()
+            .to_string()
+    };
+
+    format!("{}+{}", winner, margin)
+}
+
+fn date(line: &str) -> String {
+    match line.get(..8) {
+        Some(date) if date.bytes().all(|b| b.is_ascii_digit()) => {
+            format!("{}-{}-{}", &date[..4], &date[4..6], &date[6..])
+        }
+        _ => String::new(),
+    }
+}
+
+#[derive(Debug)]
+pub enum NgfError {
+    Truncated,
+    InvalidBoardSize(String),
+}
+
+impl std::fmt::Display for NgfError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            NgfError::Truncated => write!(f, "Game record header is incomplete."),
+            NgfError::InvalidBoardSize(size) => write!(f, "Invalid board size: {}", size),
+        }
+    }
+}
+
+impl std::error::Error for NgfError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GAME: &str = "Sample game
+19
+Cho Hunhyun 9D*
+Lee Changho 8D
+www.wbaduk.com
+0
+0
+6
+20020315 [12:00]
+0
+White wins by resignation
+3
+PMAABQE
+PMABWAA
+PMACBBB
+";
+
+    #[test]
+    fn converts_header_and_moves() {
+        // Coordinates count from `B`, so `BB` is the top left corner and `AA` is off the board.
+        assert_eq!(
+            to_sgf(GAME).unwrap(),
+            "(;GM[1]FF[4]SZ[19]PB[Lee Changho]BR[8d]PW[Cho Hunhyun]WR[9d]KM[6.5]RE[W+R]\
+             DT[2002-03-15]GN[Sample game];B[pd];W[];B[aa])"
+        );
+    }
+
+    #[test]
+    fn keeps_komi_in_handicap_games() {
+        let game = GAME.replacen("\n0\n0\n6\n", "\n2\n0\n0\n", 1);
+        let sgf = to_sgf(&game).unwrap();
+        assert!(sgf.contains("KM[0]HA[2]"), "{}", sgf);
+    }
+
+    #[test]
+    fn maps_results() {
+        assert_eq!(result("Black wins by 3.5 points"), "B+3.5");
+        assert_eq!(result("White wins by time"), "W+T");
+        assert_eq!(result("Jigo"), "");
+    }
+
+    #[test]
+    fn splits_ranks() {
+        assert_eq!(
+            split_rank("Cho Hunhyun 9D*"),
+            ("Cho Hunhyun", "9d".to_string())
+        );
+        assert_eq!(split_rank("Anonymous"), ("Anonymous", String::new()));
+    }
+
+    #[test]
+    fn rejects_bad_headers() {
+        assert!(matches!(to_sgf("Sample\n19\n"), Err(NgfError::Truncated)));
+        let game = GAME.replacen("\n19\n", "\n53\n", 1);
+        assert!(matches!(to_sgf(&game), Err(NgfError::InvalidBoardSize(_))));
+    }
+}
@@ -0,0 +1,282 @@
+//! `.ugf` and `.ugi` game records, as written by Pandanet's and other Japanese clients.
+//!
+//! Records are split into sections:
+//!
+//! ```text
+//! [Header]
+//! PlayerB=<name>,<rank>,...
+//! Hdcp=<handicap>,<komi>
+//! ...
+//! [Data]
+//! <point>,<color><move number>,<time>
+//! ...
+//! [Figure]
+//! .Text,<move number>
+//! <commentary>
+//! .EndText
+//! ```
+//!
+//! Points are two letters counting from `A`, with rows counted from the bottom of the board
+//! rather than the top as in SGF. Stones with move number 0 are setup stones, and anything off
+//! the board is a pass.
+
+use std::collections::HashMap;
+
+use super::{push_property, sgf_point};
+
+pub fn to_sgf(text: &str) -> Result<String, UgfError> {
+    let mut header = HashMap::new();
+    let mut data = vec![];
+    // Commentary by move number, with 0 for the start of the game.
+    let mut comments: HashMap<usize, String> = HashMap::new();
+    let mut section = "";
+    let mut comment: Option<(usize, String)> = None;
+    for line in text.lines() {
+        let line = line.trim_end();
+        if let Some((number, text)) = &mut comment {
+            if line == ".EndText" {
+                let existing = comments.entry(*number).or_default();
+                if !existing.is_empty() {
+                    existing.push('\n');
+                }
+                existing.push_str(text.trim_end());
+                comment = None;
+            } else {
+                text.push_str(line);
+                text.push('\n');
+            }
+            continue;
+        }
+        if let Some(name) = line.strip_prefix('[').and_then(|s| s.strip_suffix(']')) {
+            section = name;
+            continue;
+        }
+        match section {
+            "Header" => {
+                if let Some((key, value)) = line.split_once('=') {
+                    header.insert(key.trim(), value.trim());
+                }
+            }
+            "Data" => data.push(line),
+            "Figure" => {
+                if let Some(number) = line.strip_prefix(".Text,") {
+                    let number = number.split(',').next().unwrap_or_default();
+                    comment = Some((number.trim().parse().unwrap_or(0), String::new()));
+                }
+            }
+            _ => {}
+        }
+    }
+    if header.is_empty() && data.is_empty() {
+        return Err(UgfError::NoGame);
+    }
+
+    let size = header.get("Size").copied().unwrap_or("19");
+    let size: usize = match size.parse() {
+        Ok(size) if (1..=52).contains(&size) => size,
+        _ => return Err(UgfError::InvalidBoardSize(size.to_string())),
+    };
+    let mut sgf = format!("(;GM[1]FF[4]SZ[{}]", size);
+    for (color, key) in [("B", "PlayerB"), ("W", "PlayerW")] {
+        let mut fields = header.get(key).copied().unwrap_or_default().split(',');
+        push_property(
+            &mut sgf,
+            &format!("P{}", color),
+            fields.next().unwrap_or_default(),
+        );
+        push_property(
+            &mut sgf,
+            &format!("{}R", color),
+            fields.next().unwrap_or_default(),
+        );
+    }
+    let mut handicap = header.get("Hdcp").copied().unwrap_or_default().split(',');
+    let (handicap, komi) = (handicap.next(), handicap.next());
+    if let Some(komi) = komi.and_then(|komi| komi.trim().parse::<f64>().ok()) {
+        push_property(&mut sgf, "KM", &komi.to_string());
+    }
+    if let Some(handicap) = handicap.and_then(|handicap| handicap.trim().parse::<u32>().ok()) {
+        if handicap > 1 {
+            push_property(&mut sgf, "HA", &handicap.to_string());
+        }
+    }
+    push_property(&mut sgf, "RE", &result(header.get("Winner").copied()));
+    let date = header.get("Date").copied().unwrap_or_default();
+    push_property(
+        &mut sgf,
+        "DT",
+        &date.split(',').next().unwrap().replace('/', "-"),
+    );
+    push_property(
+        &mut sgf,
+        "GN",
+        header.get("Title").copied().unwrap_or_default(),
+    );
+    push_property(
+        &mut sgf,
+        "PC",
+        header.get("Place").copied().unwrap_or_default(),
+    );
+
+    let (mut black_setup, mut white_setup) = (String::new(), String::new());
+    let mut moves = String::new();
+    for line in data {
+        let fields: Vec<_> = line.split(',').collect();
+        let (point, color_and_number) = match fields[..] {
+            [point, color_and_number, ..] => (point.trim(), color_and_number.trim()),
+            _ => continue,
+        };
+        let color = match color_and_number.get(..1) {
+            Some(color @ "B") | Some(color @ "W") => color,
+            _ => continue,
+        };
+        let number: usize = color_and_number[1..].parse().unwrap_or(0);
+        let point = convert_point(point, size).unwrap_or_default();
+        if number == 0 {
+            let setup = if color == "B" {
+                &mut black_setup
+            } else {
+                &mut white_setup
+            };
+            if !point.is_empty() {
+                setup.push_str(&format!("[{}]", point));
+            }
+            continue;
+        }
+        moves.push_str(&format!(";{}[{}]", color, point));
+        if let Some(comment) = comments.get(&number) {
+            push_property(&mut moves, "C", comment);
+        }
+    }
+    for (identifier, setup) in [("AB", black_setup), ("AW", white_setup)] {
+        if !setup.is_empty() {
+            sgf.push_str(identifier);
+            sgf.push_str(&setup);
+        }
+    }
+    if let Some(comment) = comments.get(&0) {
+        push_property(&mut sgf, "C", comment);
+    }
+    sgf.push_str(&moves);
+    sgf.push(')');
+
+    Ok(sgf)
+}
+
+// Flips the row, since UGF counts them from the bottom.
+fn convert_point(point: &str, size: usize) -> Option<String> {
+    let bytes = point.as_bytes();
+    if bytes.len() != 2 {
+        return None;
+    }
+    let x = bytes[0].to_ascii_uppercase().wrapping_sub(b'A') as usize;
+    let row = bytes[1].to_ascii_uppercase().wrapping_sub(b'A') as usize;
+    if x >= size || row >= size {
+        return None;
+    }
+
+    sgf_point(x, size - 1 - row)
+}
+
+// Results look like `B,3.5`, with `C` for a win by resignation.
+fn result(value: Option<&str>) -> String {
+    let mut fields = value.unwrap_or_default().split(',').map(str::trim);
+    let winner = match fields.next() {
+        Some(winner @ "B") | Some(winner @ "W") => winner,
+        _ => return String::new(),
+    };
+    let margin = match fields.next() {
+        Some("C") => "R",
+        Some("T") => "T",
+        Some(margin) if margin.parse::<f64>().is_ok() => margin,
+        _ => "",
+    };
+
+    format!("{}+{}", winner, margin)
+}
+
+#[derive(Debug)]
+pub enum UgfError {
+    NoGame,
+    InvalidBoardSize(String),
+}
+
+impl std::fmt::Display for UgfError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            UgfError::NoGame => write!(f, "No game record found."),
+            UgfError::InvalidBoardSize(size) => write!(f, "Invalid board size: {}", size),
+        }
+    }
+}
+
+impl std::error::Error for UgfError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GAME: &str = "[Header]
+Title=Sample
+Size=19
+Hdcp=0,6.5
+PlayerB=Honinbo Shusaku,7d,
+PlayerW=Gennan Inseki,8d,
+Winner=B,2
+Date=1846/07/21,
+[Data]
+QP,B1,0
+DD,W2,0
+YY,B3,0
+[Figure]
+.Text,0
+Introduction
+.EndText
+.Text,2
+A quiet move.
+.EndText
+";
+
+    #[test]
+    fn converts_header_moves_and_commentary() {
+        // Rows count from the bottom, so `QP` is the upper right star point.
+        assert_eq!(
+            to_sgf(GAME).unwrap(),
+            "(;GM[1]FF[4]SZ[19]PB[Honinbo Shusaku]BR[7d]PW[Gennan Inseki]WR[8d]KM[6.5]RE[B+2]\
+             DT[1846-07-21]GN[Sample]C[Introduction];B[qd];W[dp]C[A quiet move.];B[])"
+        );
+    }
+
+    #[test]
+    fn converts_setup_stones() {
+        let game = "[Header]\nHdcp=2,0.5\n[Data]\nDD,B0,0\nPP,B0,0\nQD,W1,0\n";
+        assert_eq!(
+            to_sgf(game).unwrap(),
+            "(;GM[1]FF[4]SZ[19]KM[0.5]HA[2]AB[dp][pd];W[qp])"
+        );
+    }
+
+    #[test]
+    fn flips_rows_on_small_boards() {
+        assert_eq!(convert_point("AA", 9).as_deref(), Some("ai"));
+        assert_eq!(convert_point("IA", 9).as_deref(), Some("ii"));
+        assert_eq!(convert_point("AI", 9).as_deref(), Some("aa"));
+        assert_eq!(convert_point("JA", 9), None);
+    }
+
+    #[test]
+    fn maps_results() {
+        assert_eq!(result(Some("W,C")), "W+R");
+        assert_eq!(result(Some("B,T")), "B+T");
+        assert_eq!(result(Some("B,0.5")), "B+0.5");
+        assert_eq!(result(Some("D")), "");
+        assert_eq!(result(None), "");
+    }
+
+    #[test]
+    fn rejects_bad_records() {
+        assert!(matches!(to_sgf(""), Err(UgfError::NoGame)));
+        let game = "[Header]\nSize=60\n";
+        assert!(matches!(to_sgf(game), Err(UgfError::InvalidBoardSize(_))));
+    }
+}
@@ -0,0 +1,307 @@
+// Enough bits for the largest board SGF supports, with one padding bit at the end of each row.
+const WORDS: usize = (52 * 53usize).div_ceil(64);
+
+/// A fixed size set of board points.
+///
+/// Points are indexed row by row, with a stride one larger than the board width. The extra
+/// padding column is never set, so shifting by one never carries a point over to the next row.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Bitboard([u64; WORDS]);
+
+impl Bitboard {
+    pub fn empty() -> Bitboard {
+        Bitboard([0; WORDS])
+    }
+
+    pub fn single(index: usize) -> Bitboard {
+        let mut bitboard = Bitboard::empty();
+        bitboard.insert(index);
+        bitboard
+    }
+
+    pub fn contains(&self, index: usize) -> bool {
+        self.0[index / 64] & (1 << (index % 64)) != 0
+    }
+
+    pub fn insert(&mut self, index: usize) {
+        self.0[index / 64] |= 1 << (index % 64);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.iter().all(|&word| word == 0)
+    }
+
+    pub fn count(&self) -> usize {
+        self.0.iter().map(|word| word.count_ones() as usize).sum()
+    }
+
+    pub fn and(&self, other: &Bitboard) -> Bitboard {
+        self.zip_with(other, |a, b| a & b)
+    }
+
+    pub fn or(&self, other: &Bitboard) -> Bitboard {
+        self.zip_with(other, |a, b| a | b)
+    }
+
+    pub fn and_not(&self, other: &Bitboard) -> Bitboard {
+        self.zip_with(other, |a, b| a & !b)
+    }
+
+    /// Returns the set along with every point orthogonally adjacent to it.
+    ///
+    /// The result may include padding or off-board bits, so it should be masked before use.
+    pub fn dilate(&self, stride: usize) -> Bitboard {
+        self.or(&self.shift_up(1))
+            .or(&self.shift_down(1))
+            .or(&self.shift_up(stride))
+            .or(&self.shift_down(stride))
+    }
+
+    /// Returns the connected component of `within` containing the points in `self`.
+    pub fn flood_fill(&self, within: &Bitboard, stride: usize) -> Bitboard {
+        let mut filled = self.and(within);
+        loop {
+            let next = filled.dilate(stride).and(within);
+            if next == filled {
+                return filled;
+            }
+            filled = next;
+        }
+    }
+
+    pub fn first(&self) -> Option<usize> {
+        self.0
+            .iter()
+            .enumerate()
+            .find(|(_, &word)| word != 0)
+            .map(|(i, word)| i * 64 + word.trailing_zeros() as usize)
+    }
+
+    /// Splits the set into its orthogonally connected components.
+    pub fn components(&self, stride: usize) -> Vec<Bitboard> {
+        let mut components = vec![];
+        let mut remaining = *self;
+        while let Some(index) = remaining.first() {
+            let component = Bitboard::single(index).flood_fill(self, stride);
+            remaining = remaining.and_not(&component);
+            components.push(component);
+        }
+
+        components
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        self.0.iter().enumerate().flat_map(|(i, &word)| {
+            let mut word = word;
+            std::iter::from_fn(move || {
+                if word == 0 {
+                    return None;
+                }
+                let bit = word.trailing_zeros() as usize;
+                word &= word - 1;
+                Some(i * 64 + bit)
+            })
+        })
+    }
+
+    fn zip_with(&self, other: &Bitboard, f: impl Fn(u64, u64) -> u64) -> Bitboard {
+        let mut result = [0; WORDS];
+        for (i, word) in result.iter_mut().enumerate() {
+            *word = f(self.0[i], other.0[i]);
+        }
+        Bitboard(result)
+    }
+
+    // Moves every point to a higher index.
+    fn shift_up(&self, amount: usize) -> Bitboard {
+        let (words, bits) = (amount / 64, amount % 64);
+        let mut result = [0; WORDS];
+        for i in (words..WORDS).rev() {
+            result[i] = self.0[i - words] << bits;
+            if bits > 0 && i > words {
+                result[i] |= self.0[i - words - 1] >> (64 - bits);
+            }
+        }
+        Bitboard(result)
+    }
+
+    // Moves every point to a lower index.
+    fn shift_down(&self, amount: usize) -> Bitboard {
+        let (words, bits) = (amount / 64, amount % 64);
+        let mut result = [0; WORDS];
+        for (i, word) in result.iter_mut().enumerate().take(WORDS - words) {
+            *word = self.0[i + words] >> bits;
+            if bits > 0 && i + words + 1 < WORDS {
+                *word |= self.0[i + words + 1] << (64 - bits);
+            }
+        }
+        Bitboard(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    use super::*;
+    use crate::goban::{Goban, Stone, StoneColor};
+
+    fn index(point: (usize, usize), width: usize) -> usize {
+        point.1 * (width + 1) + point.0
+    }
+
+    // Surrounds a lone white stone with black, playing the capturing stone last.
+    fn capture(size: u8, white: (u8, u8)) -> Goban {
+        let mut goban = Goban::new((size, size));
+        goban
+            .play_stone(Stone::new(white.0, white.1, StoneColor::White))
+            .unwrap();
+        let (x, y) = (white.0 as i16, white.1 as i16);
+        for (nx, ny) in [(x - 1, y), (x + 1, y), (x, y - 1), (x, y + 1)] {
+            if (0..size as i16).contains(&nx) && (0..size as i16).contains(&ny) {
+                goban
+                    .play_stone(Stone::new(nx as u8, ny as u8, StoneColor::Black))
+                    .unwrap();
+            }
+        }
+        goban
+    }
+
+    #[test]
+    fn captures_on_edges_and_corners() {
+        for size in [19, 52] {
+            let last = size - 1;
+            let points = [
+                (0, 0),
+                (last, 0),
+                (0, last),
+                (last, last),
+                (0, 7),
+                (last, 7),
+                (7, 0),
+                (7, last),
+            ];
+            for point in points {
+                let goban = capture(size, point);
+                assert_eq!(
+                    goban.stone_at(point),
+                    None,
+                    "{:?} on {}×{}",
+                    point,
+                    size,
+                    size
+                );
+                assert_eq!(goban.captures(StoneColor::Black), 1);
+            }
+        }
+    }
+
+    #[test]
+    fn edge_stone_with_a_liberty_is_not_captured() {
+        for size in [19, 52] {
+            let last = size - 1;
+            let mut goban = Goban::new((size, size));
+            // The points just across the row boundary from each side must not count as neighbors.
+            for (x, y, color) in [
+                (last, 3, StoneColor::White),
+                (0, 4, StoneColor::Black),
+                (last, 2, StoneColor::Black),
+                (last - 1, 3, StoneColor::Black),
+            ] {
+                goban.play_stone(Stone::new(x, y, color)).unwrap();
+            }
+            assert_eq!(goban.stone_at((last, 3)), Some(StoneColor::White));
+        }
+    }
+
+    #[test]
+    fn groups_never_wrap_between_rows() {
+        for width in [19, 52] {
+            let end_of_row = Bitboard::single(index((width - 1, 0), width));
+            let start_of_next = Bitboard::single(index((0, 1), width));
+            let both = end_of_row.or(&start_of_next);
+
+            assert!(end_of_row.dilate(width + 1).and(&start_of_next).is_empty());
+            assert_eq!(both.components(width + 1).len(), 2);
+            assert_eq!(end_of_row.flood_fill(&both, width + 1), end_of_row);
+        }
+    }
+
+    fn naive_components(points: &HashSet<(usize, usize)>) -> Vec<HashSet<(usize, usize)>> {
+        let mut remaining = points.clone();
+        let mut components = vec![];
+        while let Some(&start) = remaining.iter().next() {
+            let mut component = HashSet::new();
+            let mut stack = vec![start];
+            while let Some((x, y)) = stack.pop() {
+                if !remaining.remove(&(x, y)) {
+                    continue;
+                }
+                component.insert((x, y));
+                stack.push((x + 1, y));
+                stack.push((x, y + 1));
+                if x > 0 {
+                    stack.push((x - 1, y));
+                }
+                if y > 0 {
+                    stack.push((x, y - 1));
+                }
+            }
+            components.push(component);
+        }
+        components
+    }
+
+    #[test]
+    fn components_match_naive_flood_fill() {
+        let mut rng = StdRng::seed_from_u64(5);
+        for width in [19, 52] {
+            for _ in 0..20 {
+                let points: HashSet<_> = (0..width)
+                    .flat_map(|x| (0..width).map(move |y| (x, y)))
+                    .filter(|_| rng.gen_bool(0.45))
+                    .collect();
+                let mut bitboard = Bitboard::empty();
+                for &point in &points {
+                    bitboard.insert(index(point, width));
+                }
+                let to_points = |component: &Bitboard| -> Vec<(usize, usize)> {
+                    let mut points: Vec<_> = component
+                        .iter()
+                        .map(|i| (i % (width + 1), i / (width + 1)))
+                        .collect();
+                    points.sort_unstable();
+                    points
+                };
+
+                let mut expected: Vec<_> = naive_components(&points)
+                    .iter()
+                    .map(|component| {
+                        let mut points: Vec<_> = component.iter().copied().collect();
+                        points.sort_unstable();
+                        points
+                    })
+                    .collect();
+                let mut actual: Vec<_> = bitboard
+                    .components(width + 1)
+                    .iter()
+                    .map(to_points)
+                    .collect();
+                expected.sort();
+                actual.sort();
+                assert_eq!(actual, expected);
+
+                for component in &expected {
+                    let seed = Bitboard::single(index(component[0], width));
+                    assert_eq!(
+                        to_points(&seed.flood_fill(&bitboard, width + 1)),
+                        *component
+                    );
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,78 @@
+use super::bitboard::Bitboard;
+use super::{Goban, StoneColor};
+
+/// A maximal set of connected stones of the same color.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Group {
+    pub color: StoneColor,
+    pub stones: Vec<(u8, u8)>,
+    pub liberties: Vec<(u8, u8)>,
+}
+
+impl Group {
+    pub fn is_in_atari(&self) -> bool {
+        self.liberties.len() == 1
+    }
+}
+
+impl Goban {
+    /// Returns the group containing the stone at `point`, if there is one.
+    pub fn group_at(&self, point: (u8, u8)) -> Option<Group> {
+        let color = self.stone_at(point)?;
+        let stones = self.group_stones(self.index(point), color);
+
+        Some(self.make_group(&stones, color))
+    }
+
+    /// Returns the liberties of the group containing the stone at `point`.
+    ///
+    /// Returns an empty list if there is no stone at `point`.
+    pub fn liberties(&self, point: (u8, u8)) -> Vec<(u8, u8)> {
+        match self.stone_at(point) {
+            Some(color) => {
+                let stones = self.group_stones(self.index(point), color);
+                self.points(&self.group_liberties(&stones))
+            }
+            None => vec![],
+        }
+    }
+
+    /// Returns the number of liberties of the group containing the stone at `point`.
+    pub fn liberty_count(&self, point: (u8, u8)) -> usize {
+        match self.stone_at(point) {
+            Some(color) => {
+                let stones = self.group_stones(self.index(point), color);
+                self.group_liberties(&stones).count()
+            }
+            None => 0,
+        }
+    }
+
+    /// Returns every group on the board.
+    pub fn groups(&self) -> Vec<Group> {
+        let mut groups = vec![];
+        for &color in &[StoneColor::Black, StoneColor::White] {
+            for stones in self.stones_of(color).components(self.stride()) {
+                groups.push(self.make_group(&stones, color));
+            }
+        }
+
+        groups
+    }
+
+    /// Returns every group with exactly one liberty.
+    pub fn groups_in_atari(&self) -> Vec<Group> {
+        self.groups()
+            .into_iter()
+            .filter(Group::is_in_atari)
+            .collect()
+    }
+
+    fn make_group(&self, stones: &Bitboard, color: StoneColor) -> Group {
+        Group {
+            color,
+            stones: self.points(stones),
+            liberties: self.points(&self.group_liberties(stones)),
+        }
+    }
+}
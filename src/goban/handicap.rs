@@ -0,0 +1,75 @@
+use super::{Goban, GobanError, Stone, StoneColor};
+
+impl Goban {
+    /// Returns the traditional fixed handicap points for the board, if it has any.
+    ///
+    /// Fixed placement is only defined for 2 to 9 stones on 9x9, 13x13 and 19x19 boards.
+    pub fn fixed_handicap_points(&self, count: u8) -> Option<Vec<(u8, u8)>> {
+        let edge_distance = match self.size {
+            (9, 9) => 2,
+            (13, 13) | (19, 19) => 3,
+            _ => return None,
+        };
+        if !(2..=9).contains(&count) {
+            return None;
+        }
+        let near = edge_distance;
+        let far = self.size.0 - 1 - edge_distance;
+        let center = self.size.0 / 2;
+        // Upper right and lower left first, so black's stones face white's first move.
+        let mut points = vec![(far, near), (near, far), (far, far), (near, near)];
+        points.truncate(count as usize);
+        if count >= 6 {
+            points.extend(&[(near, center), (far, center)]);
+        }
+        if count >= 8 {
+            points.extend(&[(center, near), (center, far)]);
+        }
+        if count % 2 == 1 && count >= 5 {
+            points.push((center, center));
+        }
+
+        Some(points)
+    }
+
+    /// Sets up a handicap with fixed placement, leaving white to play.
+    pub fn place_handicap(&mut self, count: u8) -> Result<(), GobanError> {
+        let points = self
+            .fixed_handicap_points(count)
+            .ok_or(GobanError::InvalidHandicap(count))?;
+        for (x, y) in points {
+            self.add_stone(Stone::new(x, y, StoneColor::Black))?;
+        }
+        self.to_move = StoneColor::White;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fixed_handicap_leaves_white_to_play() {
+        let mut goban = Goban::new((19, 19));
+        goban.place_handicap(3).unwrap();
+        assert_eq!(goban.to_move, StoneColor::White);
+        assert_eq!(goban.stone_at((15, 3)), Some(StoneColor::Black));
+        assert_eq!(goban.stone_at((3, 15)), Some(StoneColor::Black));
+        assert_eq!(goban.stone_at((15, 15)), Some(StoneColor::Black));
+
+        goban.pass(StoneColor::White);
+        assert_eq!(goban.to_move, StoneColor::Black);
+    }
+
+    #[test]
+    fn no_fixed_placement_on_odd_sizes() {
+        let mut goban = Goban::new((11, 11));
+        assert!(matches!(
+            goban.place_handicap(2),
+            Err(GobanError::InvalidHandicap(2))
+        ));
+        assert_eq!(goban.to_move, StoneColor::Black);
+    }
+}
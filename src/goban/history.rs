@@ -0,0 +1,82 @@
+use super::Goban;
+
+/// Snapshots of a `Goban` after each node played, so that play can be stepped backwards.
+pub struct GobanHistory {
+    positions: Vec<Goban>,
+    current: usize,
+}
+
+impl GobanHistory {
+    pub fn new(goban: Goban) -> GobanHistory {
+        GobanHistory {
+            positions: vec![goban],
+            current: 0,
+        }
+    }
+
+    pub fn current(&self) -> &Goban {
+        &self.positions[self.current]
+    }
+
+    pub fn current_index(&self) -> usize {
+        self.current
+    }
+
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+
+    /// Adds a new position after the current one, discarding any positions that followed it.
+    pub fn push(&mut self, goban: Goban) {
+        self.positions.truncate(self.current + 1);
+        self.positions.push(goban);
+        self.current += 1;
+    }
+
+    pub fn step_back(&mut self) -> bool {
+        if self.current == 0 {
+            return false;
+        }
+        self.current -= 1;
+
+        true
+    }
+
+    pub fn step_forward(&mut self) -> bool {
+        if self.current + 1 >= self.positions.len() {
+            return false;
+        }
+        self.current += 1;
+
+        true
+    }
+
+    pub fn go_to(&mut self, index: usize) -> bool {
+        if index >= self.positions.len() {
+            return false;
+        }
+        self.current = index;
+
+        true
+    }
+
+    pub fn go_to_end(&mut self) {
+        self.current = self.positions.len() - 1;
+    }
+
+    /// Moves to the first position with the given move number.
+    pub fn go_to_move(&mut self, move_number: u64) -> bool {
+        match self
+            .positions
+            .iter()
+            .position(|goban| goban.move_number == move_number)
+        {
+            Some(index) => self.go_to(index),
+            None => false,
+        }
+    }
+}
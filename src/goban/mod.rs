@@ -0,0 +1,609 @@
+mod bitboard;
+mod group;
+mod handicap;
+mod history;
+mod influence;
+mod position_history;
+mod scoring;
+
+use bitboard::Bitboard;
+use position_history::PositionHistory;
+
+pub use group::Group;
+pub use history::GobanHistory;
+pub use influence::Influence;
+pub use scoring::{GameResult, Score, ScoringRule, WinMargin};
+
+/// The largest board size representable in SGF.
+pub const MAX_BOARD_SIZE: u8 = 52;
+
+#[derive(Clone)]
+pub struct Goban {
+    pub size: (u8, u8),
+    black_stones: Bitboard,
+    white_stones: Bitboard,
+    on_board: Bitboard,
+    pub move_number: u64,
+    /// The player to move next, as set by play, fixed handicap placement or the SGF `PL` property.
+    pub to_move: StoneColor,
+    black_captures: u64,
+    white_captures: u64,
+    pub rules: Rules,
+    ko_point: Option<Stone>,
+    hash: u64,
+    // Zobrist hashes of every position seen before a move, along with the player to move.
+    history: PositionHistory,
+}
+
+impl Goban {
+    /// Returns an empty board.
+    ///
+    /// Panics if either dimension is larger than `MAX_BOARD_SIZE`.
+    pub fn new(board_size: (u8, u8)) -> Goban {
+        assert!(
+            board_size.0 <= MAX_BOARD_SIZE && board_size.1 <= MAX_BOARD_SIZE,
+            "Board size {:?} is too large",
+            board_size
+        );
+        let mut goban = Goban {
+            size: board_size,
+            black_stones: Bitboard::empty(),
+            white_stones: Bitboard::empty(),
+            on_board: Bitboard::empty(),
+            move_number: 0,
+            to_move: StoneColor::Black,
+            black_captures: 0,
+            white_captures: 0,
+            rules: Rules::default(),
+            ko_point: None,
+            hash: 0,
+            history: PositionHistory::default(),
+        };
+        for x in 0..board_size.0 {
+            for y in 0..board_size.1 {
+                let index = goban.index((x, y));
+                goban.on_board.insert(index);
+            }
+        }
+
+        goban
+    }
+
+    pub fn stones(&self) -> impl Iterator<Item = Stone> + '_ {
+        let black = self.black_stones.iter().map(move |index| {
+            let (x, y) = self.point(index);
+            Stone::new(x, y, StoneColor::Black)
+        });
+        let white = self.white_stones.iter().map(move |index| {
+            let (x, y) = self.point(index);
+            Stone::new(x, y, StoneColor::White)
+        });
+
+        black.chain(white)
+    }
+
+    pub fn stone_at(&self, point: (u8, u8)) -> Option<StoneColor> {
+        if !self.is_on_board(point) {
+            return None;
+        }
+        let index = self.index(point);
+        if self.black_stones.contains(index) {
+            Some(StoneColor::Black)
+        } else if self.white_stones.contains(index) {
+            Some(StoneColor::White)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the number of prisoners taken by the given player.
+    pub fn captures(&self, color: StoneColor) -> u64 {
+        match color {
+            StoneColor::Black => self.black_captures,
+            StoneColor::White => self.white_captures,
+        }
+    }
+
+    pub fn add_stone(&mut self, stone: Stone) -> Result<(), GobanError> {
+        self.place_stone(stone)?;
+        self.reset_after_setup();
+
+        Ok(())
+    }
+
+    pub fn play_stone(&mut self, stone: Stone) -> Result<(), GobanError> {
+        if self.ko_point == Some(stone) {
+            return Err(GobanError::Ko(stone.x, stone.y));
+        }
+        let previous_hash = self.hash;
+        self.place_stone(stone)?;
+        let index = self.index((stone.x, stone.y));
+        let opponent_color = stone.color.opponent();
+        // Remove any neighboring groups with no liberties.
+        let neighbors = Bitboard::single(index)
+            .dilate(self.stride())
+            .and(self.stones_of(opponent_color));
+        let mut captured = Bitboard::empty();
+        for neighbor in neighbors.iter() {
+            if !captured.contains(neighbor) {
+                captured = captured.or(&self.dead_group(neighbor, opponent_color));
+            }
+        }
+        self.remove_stones(&captured, opponent_color);
+        // Now remove the played stone if still neccessary
+        let suicided = self.dead_group(index, stone.color);
+        self.remove_stones(&suicided, stone.color);
+
+        let suicide_error = match suicided.count() {
+            0 => None,
+            1 => Some(GobanError::Suicide(stone.x, stone.y)),
+            _ => Some(GobanError::Suicide(stone.x, stone.y))
+                .filter(|_| !self.rules.multi_stone_suicide),
+        };
+        let repetition_error = || match self.rules.ko_rule {
+            KoRule::Simple => None,
+            KoRule::PositionalSuperko => Some(GobanError::PositionalSuperko)
+                .filter(|_| self.history.contains_position(self.hash)),
+            KoRule::SituationalSuperko => Some(GobanError::SituationalSuperko)
+                .filter(|_| self.history.contains_situation(self.hash, opponent_color)),
+        };
+        if let Some(error) = suicide_error.or_else(repetition_error) {
+            // Put the board back the way it was before the move.
+            self.add_stones(&suicided, stone.color);
+            self.remove_stones(&Bitboard::single(index), stone.color);
+            self.add_stones(&captured, opponent_color);
+            return Err(error);
+        }
+
+        // The situation is recorded with whoever the board says was to move, which setup with `PL`
+        // can make different from the player who actually moved.
+        self.history.push(previous_hash, self.to_move);
+        // A single stone capturing a single stone and left in atari is a ko.
+        self.ko_point = match captured.count() {
+            1 if self.is_lone_stone_in_atari(index) => captured.first().map(|ko_index| {
+                let (x, y) = self.point(ko_index);
+                Stone::new(x, y, opponent_color)
+            }),
+            _ => None,
+        };
+        // Stones lost to suicide are prisoners for the opponent.
+        self.add_captures(stone.color, captured.count() as u64);
+        self.add_captures(opponent_color, suicided.count() as u64);
+        self.move_number += 1;
+        self.to_move = opponent_color;
+
+        Ok(())
+    }
+
+    pub fn pass(&mut self, color: StoneColor) {
+        self.history.push(self.hash, self.to_move);
+        self.ko_point = None;
+        self.move_number += 1;
+        self.to_move = color.opponent();
+    }
+
+    pub fn clear_point(&mut self, point: (u8, u8)) {
+        if let Some(color) = self.stone_at(point) {
+            let index = self.index(point);
+            self.remove_stones(&Bitboard::single(index), color);
+        }
+        self.reset_after_setup();
+    }
+
+    pub fn set_move_number(&mut self, num: u64) {
+        self.move_number = num;
+    }
+
+    // Setup properties set up a new position rather than playing into it, so stones they add or
+    // remove are never prisoners, and the prisoner counts from earlier play no longer apply.
+    fn reset_after_setup(&mut self) {
+        self.ko_point = None;
+        self.black_captures = 0;
+        self.white_captures = 0;
+    }
+
+    fn add_captures(&mut self, color: StoneColor, count: u64) {
+        match color {
+            StoneColor::Black => self.black_captures += count,
+            StoneColor::White => self.white_captures += count,
+        }
+    }
+
+    fn place_stone(&mut self, stone: Stone) -> Result<(), GobanError> {
+        let point = (stone.x, stone.y);
+        if !self.is_on_board(point) {
+            return Err(GobanError::OutOfBounds(stone.x, stone.y));
+        }
+        if self.stone_at(point).is_some() {
+            return Err(GobanError::OccupiedPoint(stone.x, stone.y));
+        }
+        let index = self.index(point);
+        self.add_stones(&Bitboard::single(index), stone.color);
+
+        Ok(())
+    }
+
+    fn add_stones(&mut self, stones: &Bitboard, color: StoneColor) {
+        for index in stones.iter() {
+            self.hash ^= Goban::zobrist_key(self.point(index), color);
+        }
+        let updated = self.stones_of(color).or(stones);
+        *self.stones_of_mut(color) = updated;
+    }
+
+    fn remove_stones(&mut self, stones: &Bitboard, color: StoneColor) {
+        for index in stones.iter() {
+            self.hash ^= Goban::zobrist_key(self.point(index), color);
+        }
+        let updated = self.stones_of(color).and_not(stones);
+        *self.stones_of_mut(color) = updated;
+    }
+
+    fn stones_of(&self, color: StoneColor) -> &Bitboard {
+        match color {
+            StoneColor::Black => &self.black_stones,
+            StoneColor::White => &self.white_stones,
+        }
+    }
+
+    fn stones_of_mut(&mut self, color: StoneColor) -> &mut Bitboard {
+        match color {
+            StoneColor::Black => &mut self.black_stones,
+            StoneColor::White => &mut self.white_stones,
+        }
+    }
+
+    fn empty_points(&self) -> Bitboard {
+        self.on_board
+            .and_not(&self.black_stones.or(&self.white_stones))
+    }
+
+    fn group_stones(&self, index: usize, color: StoneColor) -> Bitboard {
+        Bitboard::single(index).flood_fill(self.stones_of(color), self.stride())
+    }
+
+    fn group_liberties(&self, group: &Bitboard) -> Bitboard {
+        group.dilate(self.stride()).and(&self.empty_points())
+    }
+
+    // Returns the group at `index` if it has no liberties, and an empty set otherwise.
+    fn dead_group(&self, index: usize, color: StoneColor) -> Bitboard {
+        let group = self.group_stones(index, color);
+        if self.group_liberties(&group).is_empty() {
+            group
+        } else {
+            Bitboard::empty()
+        }
+    }
+
+    fn is_lone_stone_in_atari(&self, index: usize) -> bool {
+        let color = match self.stone_at(self.point(index)) {
+            Some(color) => color,
+            None => return false,
+        };
+        let group = self.group_stones(index, color);
+
+        group.count() == 1 && self.group_liberties(&group).count() == 1
+    }
+
+    fn points(&self, bitboard: &Bitboard) -> Vec<(u8, u8)> {
+        bitboard.iter().map(|index| self.point(index)).collect()
+    }
+
+    fn is_on_board(&self, point: (u8, u8)) -> bool {
+        point.0 < self.size.0 && point.1 < self.size.1
+    }
+
+    fn stride(&self) -> usize {
+        self.size.0 as usize + 1
+    }
+
+    fn index(&self, point: (u8, u8)) -> usize {
+        point.1 as usize * self.stride() + point.0 as usize
+    }
+
+    fn point(&self, index: usize) -> (u8, u8) {
+        ((index % self.stride()) as u8, (index / self.stride()) as u8)
+    }
+
+    // Deterministic pseudo-random key for a stone of the given color on the given point.
+    fn zobrist_key(point: (u8, u8), color: StoneColor) -> u64 {
+        let index = (point.0 as u64) << 8 | point.1 as u64;
+        let color_bit = match color {
+            StoneColor::Black => 0,
+            StoneColor::White => 1,
+        };
+        splitmix64(index << 1 | color_bit)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Rules {
+    pub ko_rule: KoRule,
+    pub multi_stone_suicide: bool,
+    pub scoring_rule: ScoringRule,
+}
+
+impl Rules {
+    /// Returns the rules for a ruleset name as used by the SGF `RU` property.
+    ///
+    /// Unrecognized names get the default (Japanese style) rules.
+    pub fn from_name(name: &str) -> Rules {
+        let (ko_rule, multi_stone_suicide) = match name.trim().to_lowercase().as_str() {
+            "chinese" => (KoRule::PositionalSuperko, false),
+            "aga" => (KoRule::SituationalSuperko, false),
+            "nz" | "new zealand" => (KoRule::SituationalSuperko, true),
+            "goe" | "ing" => (KoRule::SituationalSuperko, true),
+            "tromp-taylor" | "tromp taylor" => (KoRule::PositionalSuperko, true),
+            _ => return Rules::default(),
+        };
+        // Every ruleset with superko also uses area scoring.
+        Rules {
+            ko_rule,
+            multi_stone_suicide,
+            scoring_rule: ScoringRule::Area,
+        }
+    }
+}
+
+impl Default for Rules {
+    fn default() -> Rules {
+        Rules {
+            ko_rule: KoRule::Simple,
+            multi_stone_suicide: false,
+            scoring_rule: ScoringRule::Territory,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum KoRule {
+    Simple,
+    PositionalSuperko,
+    SituationalSuperko,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum StoneColor {
+    Black,
+    White,
+}
+
+impl StoneColor {
+    pub fn opponent(self) -> StoneColor {
+        match self {
+            StoneColor::Black => StoneColor::White,
+            StoneColor::White => StoneColor::Black,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Stone {
+    pub x: u8,
+    pub y: u8,
+    pub color: StoneColor,
+}
+
+impl Stone {
+    pub fn new(x: u8, y: u8, color: StoneColor) -> Stone {
+        Stone { x, y, color }
+    }
+}
+
+#[derive(Debug)]
+pub enum GobanError {
+    InvalidBoardSize(u8, u8),
+    OutOfBounds(u8, u8),
+    OccupiedPoint(u8, u8),
+    Suicide(u8, u8),
+    Ko(u8, u8),
+    InvalidHandicap(u8),
+    PositionalSuperko,
+    SituationalSuperko,
+}
+
+impl std::fmt::Display for GobanError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            GobanError::InvalidBoardSize(width, height) => {
+                write!(f, "Unsupported board size {}x{}", width, height)
+            }
+            GobanError::OutOfBounds(x, y) => write!(f, "Point ({}, {}) is off the board", x, y),
+            GobanError::OccupiedPoint(x, y) => {
+                write!(f, "Point ({}, {}) is already occupied", x, y)
+            }
+            GobanError::Suicide(x, y) => write!(f, "Illegal suicide at ({}, {})", x, y),
+            GobanError::Ko(x, y) => write!(f, "Illegal ko recapture at ({}, {})", x, y),
+            GobanError::InvalidHandicap(count) => {
+                write!(f, "No fixed placement for a handicap of {}", count)
+            }
+            GobanError::PositionalSuperko => write!(f, "Move repeats an earlier position"),
+            GobanError::SituationalSuperko => {
+                write!(
+                    f,
+                    "Move repeats an earlier position with the same player to move"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for GobanError {}
+
+fn splitmix64(seed: u64) -> u64 {
+    let mut z = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn board(size: u8, black: &[(u8, u8)], white: &[(u8, u8)]) -> Goban {
+        let mut goban = Goban::new((size, size));
+        for &(x, y) in black {
+            goban
+                .add_stone(Stone::new(x, y, StoneColor::Black))
+                .unwrap();
+        }
+        for &(x, y) in white {
+            goban
+                .add_stone(Stone::new(x, y, StoneColor::White))
+                .unwrap();
+        }
+        goban
+    }
+
+    // Black can take a ko at (2, 1) by capturing the white stone at (1, 1).
+    fn ko(rules: Rules) -> Goban {
+        let mut goban = board(
+            5,
+            &[(1, 0), (0, 1), (1, 2)],
+            &[(2, 0), (1, 1), (3, 1), (2, 2)],
+        );
+        goban.rules = rules;
+        goban
+    }
+
+    fn play(goban: &mut Goban, x: u8, y: u8, color: StoneColor) -> Result<(), GobanError> {
+        goban.play_stone(Stone::new(x, y, color))
+    }
+
+    #[test]
+    fn ko_recapture_waits_for_a_move_elsewhere() {
+        let mut goban = ko(Rules::default());
+        play(&mut goban, 2, 1, StoneColor::Black).unwrap();
+        assert_eq!(goban.stone_at((1, 1)), None);
+        assert!(matches!(
+            play(&mut goban, 1, 1, StoneColor::White),
+            Err(GobanError::Ko(1, 1))
+        ));
+
+        play(&mut goban, 4, 4, StoneColor::White).unwrap();
+        play(&mut goban, 4, 3, StoneColor::Black).unwrap();
+        play(&mut goban, 1, 1, StoneColor::White).unwrap();
+        assert_eq!(goban.stone_at((2, 1)), None);
+        assert_eq!(goban.captures(StoneColor::White), 1);
+    }
+
+    #[test]
+    fn snapback_is_not_a_ko() {
+        let mut goban = board(5, &[(2, 0), (2, 1), (1, 2), (0, 2)], &[(0, 1), (1, 1)]);
+        play(&mut goban, 0, 0, StoneColor::Black).unwrap();
+        // White captures the single stone, but the capturing stone joins a group.
+        play(&mut goban, 1, 0, StoneColor::White).unwrap();
+        assert_eq!(goban.stone_at((0, 0)), None);
+        assert_eq!(goban.ko_point, None);
+
+        play(&mut goban, 0, 0, StoneColor::Black).unwrap();
+        assert_eq!(goban.captures(StoneColor::Black), 3);
+        assert_eq!(goban.ko_point, None);
+    }
+
+    // Black takes the ko, both players pass, and white retakes it, repeating the position from
+    // before black took it.
+    fn retake_after_passes(goban: &mut Goban) -> Result<(), GobanError> {
+        play(goban, 2, 1, StoneColor::Black).unwrap();
+        goban.pass(StoneColor::White);
+        goban.pass(StoneColor::Black);
+        play(goban, 1, 1, StoneColor::White)
+    }
+
+    #[test]
+    fn positional_superko_forbids_repeats() {
+        let mut goban = ko(Rules::default());
+        assert!(retake_after_passes(&mut goban).is_ok());
+
+        let mut goban = ko(Rules::from_name("chinese"));
+        play(&mut goban, 2, 1, StoneColor::Black).unwrap();
+        goban.pass(StoneColor::White);
+        goban.pass(StoneColor::Black);
+        let hash = goban.hash;
+        assert!(matches!(
+            play(&mut goban, 1, 1, StoneColor::White),
+            Err(GobanError::PositionalSuperko)
+        ));
+        // The rejected move is undone.
+        assert_eq!(goban.hash, hash);
+        assert_eq!(goban.stone_at((1, 1)), None);
+        assert_eq!(goban.stone_at((2, 1)), Some(StoneColor::Black));
+        assert_eq!(goban.captures(StoneColor::White), 0);
+    }
+
+    #[test]
+    fn situational_superko_depends_on_player_to_move() {
+        let mut goban = ko(Rules::from_name("aga"));
+        assert!(matches!(
+            retake_after_passes(&mut goban),
+            Err(GobanError::SituationalSuperko)
+        ));
+
+        // As if the record set white to play (with `PL`) before black took the ko, so the first
+        // time round the position had white to move, and this time black.
+        let mut goban = ko(Rules::from_name("aga"));
+        goban.to_move = StoneColor::White;
+        assert!(retake_after_passes(&mut goban).is_ok());
+
+        let mut goban = ko(Rules::from_name("chinese"));
+        goban.to_move = StoneColor::White;
+        assert!(matches!(
+            retake_after_passes(&mut goban),
+            Err(GobanError::PositionalSuperko)
+        ));
+    }
+
+    #[test]
+    fn rejected_suicide_leaves_board_unchanged() {
+        for rules in [Rules::default(), Rules::from_name("nz")] {
+            let mut goban = board(5, &[], &[(1, 0), (0, 1)]);
+            goban.rules = rules;
+            let hash = goban.hash;
+            // Single stone suicide is illegal even where multi-stone suicide is allowed.
+            assert!(matches!(
+                play(&mut goban, 0, 0, StoneColor::Black),
+                Err(GobanError::Suicide(0, 0))
+            ));
+            assert_eq!(goban.stone_at((0, 0)), None);
+            assert_eq!(goban.hash, hash);
+            assert_eq!(goban.move_number, 0);
+        }
+
+        let mut goban = board(5, &[(1, 0)], &[(2, 0), (1, 1), (0, 1)]);
+        let hash = goban.hash;
+        assert!(matches!(
+            play(&mut goban, 0, 0, StoneColor::Black),
+            Err(GobanError::Suicide(0, 0))
+        ));
+        assert_eq!(goban.stone_at((0, 0)), None);
+        assert_eq!(goban.stone_at((1, 0)), Some(StoneColor::Black));
+        assert_eq!(goban.hash, hash);
+        assert_eq!(goban.captures(StoneColor::White), 0);
+    }
+
+    #[test]
+    fn multi_stone_suicide_credits_opponent() {
+        let mut goban = board(5, &[(1, 0)], &[(2, 0), (1, 1), (0, 1)]);
+        goban.rules = Rules::from_name("nz");
+        play(&mut goban, 0, 0, StoneColor::Black).unwrap();
+
+        assert_eq!(goban.stone_at((0, 0)), None);
+        assert_eq!(goban.stone_at((1, 0)), None);
+        assert_eq!(goban.captures(StoneColor::White), 2);
+        assert_eq!(goban.captures(StoneColor::Black), 0);
+        // The hash is back to that of the white stones alone.
+        let white_only = board(5, &[], &[(2, 0), (1, 1), (0, 1)]);
+        assert_eq!(goban.hash, white_only.hash);
+    }
+
+    #[test]
+    fn capturing_is_not_suicide() {
+        // The black stone at (0, 0) has no liberties of its own until it captures (1, 0).
+        let mut goban = board(5, &[(2, 0), (1, 1)], &[(1, 0), (0, 1), (0, 2)]);
+        play(&mut goban, 0, 0, StoneColor::Black).unwrap();
+        assert_eq!(goban.stone_at((1, 0)), None);
+        assert_eq!(goban.captures(StoneColor::Black), 1);
+    }
+}
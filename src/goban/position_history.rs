@@ -0,0 +1,111 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use super::StoneColor;
+
+/// The positions seen so far in a game, for superko checks.
+///
+/// Copies of a board (like the snapshots in `GobanHistory`) share one log of positions, and each
+/// only looks at the part of it recorded before the copy was made. The log is only copied when
+/// play continues from an earlier copy, as it does when switching to a variation, so replaying a
+/// game doesn't copy the history at every move.
+#[derive(Clone, Default)]
+pub struct PositionHistory {
+    log: Arc<Mutex<Log>>,
+    // Number of entries in the log which belong to this board.
+    len: usize,
+}
+
+#[derive(Default)]
+struct Log {
+    // Where each position and each (position, player to move) pair first appears in the log.
+    positions: HashMap<u64, usize>,
+    situations: HashMap<u64, usize>,
+    len: usize,
+}
+
+impl PositionHistory {
+    /// Records a position, along with the player to move from it.
+    pub fn push(&mut self, hash: u64, to_move: StoneColor) {
+        let mut log = self.log.lock().unwrap();
+        if log.len != self.len {
+            // Another copy has carried on from here, so take our own copy of our part.
+            let own_log = log.truncated(self.len);
+            drop(log);
+            self.log = Arc::new(Mutex::new(own_log));
+            log = self.log.lock().unwrap();
+        }
+        let index = log.len;
+        log.positions.entry(hash).or_insert(index);
+        log.situations
+            .entry(situation_key(hash, to_move))
+            .or_insert(index);
+        log.len += 1;
+        self.len += 1;
+    }
+
+    pub fn contains_position(&self, hash: u64) -> bool {
+        let log = self.log.lock().unwrap();
+        log.positions.get(&hash).is_some_and(|&i| i < self.len)
+    }
+
+    pub fn contains_situation(&self, hash: u64, to_move: StoneColor) -> bool {
+        let log = self.log.lock().unwrap();
+        log.situations
+            .get(&situation_key(hash, to_move))
+            .is_some_and(|&i| i < self.len)
+    }
+}
+
+impl Log {
+    fn truncated(&self, len: usize) -> Log {
+        let keep = |entries: &HashMap<u64, usize>| {
+            entries
+                .iter()
+                .filter(|&(_, &i)| i < len)
+                .map(|(&key, &i)| (key, i))
+                .collect()
+        };
+        Log {
+            positions: keep(&self.positions),
+            situations: keep(&self.situations),
+            len,
+        }
+    }
+}
+
+fn situation_key(hash: u64, to_move: StoneColor) -> u64 {
+    match to_move {
+        StoneColor::Black => hash,
+        // Any fixed random looking constant keeps the two players' keys apart.
+        StoneColor::White => hash ^ 0x9e37_79b9_7f4a_7c15,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn copies_only_see_positions_from_before_they_were_made() {
+        let mut history = PositionHistory::default();
+        history.push(1, StoneColor::Black);
+        let mut copy = history.clone();
+        history.push(2, StoneColor::White);
+        copy.push(3, StoneColor::White);
+
+        assert!(history.contains_position(1) && history.contains_position(2));
+        assert!(!history.contains_position(3));
+        assert!(copy.contains_position(1) && copy.contains_position(3));
+        assert!(!copy.contains_position(2));
+    }
+
+    #[test]
+    fn situations_depend_on_the_player_to_move() {
+        let mut history = PositionHistory::default();
+        history.push(1, StoneColor::Black);
+
+        assert!(history.contains_situation(1, StoneColor::Black));
+        assert!(!history.contains_situation(1, StoneColor::White));
+    }
+}
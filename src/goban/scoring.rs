@@ -0,0 +1,337 @@
+use super::bitboard::Bitboard;
+use super::{Goban, Stone, StoneColor};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ScoringRule {
+    /// Stones on the board plus surrounded empty points (Chinese style).
+    Area,
+    /// Surrounded empty points plus prisoners (Japanese style).
+    Territory,
+}
+
+#[derive(Clone, Debug)]
+pub struct Score {
+    pub scoring_rule: ScoringRule,
+    pub komi: f64,
+    pub black_territory: Vec<(u8, u8)>,
+    pub white_territory: Vec<(u8, u8)>,
+    pub dead_stones: Vec<Stone>,
+    pub black_points: f64,
+    /// White's points, including komi.
+    pub white_points: f64,
+}
+
+impl Score {
+    pub fn result(&self) -> GameResult {
+        let margin = self.black_points - self.white_points;
+        if margin > 0.0 {
+            GameResult::Win(StoneColor::Black, WinMargin::Points(margin))
+        } else if margin < 0.0 {
+            GameResult::Win(StoneColor::White, WinMargin::Points(-margin))
+        } else {
+            GameResult::Draw
+        }
+    }
+
+    /// Returns whether the score matches a recorded result.
+    ///
+    /// Results recorded without a margin only need the same winner.
+    pub fn agrees_with(&self, recorded: &GameResult) -> bool {
+        match (self.result(), recorded) {
+            (GameResult::Draw, GameResult::Draw) => true,
+            (GameResult::Win(winner, WinMargin::Points(margin)), GameResult::Win(color, m)) => {
+                winner == *color
+                    && match m {
+                        WinMargin::Points(recorded_margin) => {
+                            (margin - recorded_margin).abs() < 1e-6
+                        }
+                        WinMargin::Unknown => true,
+                        _ => false,
+                    }
+            }
+            _ => false,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum GameResult {
+    Draw,
+    Win(StoneColor, WinMargin),
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum WinMargin {
+    Points(f64),
+    Resignation,
+    Time,
+    Forfeit,
+    Unknown,
+}
+
+impl GameResult {
+    /// Parses a result in the format of the SGF `RE` property.
+    ///
+    /// Returns `None` for void games and unknown results.
+    pub fn parse(text: &str) -> Option<GameResult> {
+        let text = text.trim();
+        match text {
+            "0" | "Draw" | "Jigo" => return Some(GameResult::Draw),
+            _ => {}
+        }
+        let winner = match text.get(..2)? {
+            "B+" => StoneColor::Black,
+            "W+" => StoneColor::White,
+            _ => return None,
+        };
+        let margin = match &text[2..] {
+            "" => WinMargin::Unknown,
+            "R" | "Resign" => WinMargin::Resignation,
+            "T" | "Time" => WinMargin::Time,
+            "F" | "Forfeit" => WinMargin::Forfeit,
+            points => WinMargin::Points(points.parse().ok()?),
+        };
+
+        Some(GameResult::Win(winner, margin))
+    }
+}
+
+impl std::fmt::Display for GameResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            GameResult::Draw => write!(f, "Draw"),
+            GameResult::Win(color, margin) => {
+                let color = match color {
+                    StoneColor::Black => "B",
+                    StoneColor::White => "W",
+                };
+                match margin {
+                    WinMargin::Points(points) => write!(f, "{}+{}", color, points),
+                    WinMargin::Resignation => write!(f, "{}+R", color),
+                    WinMargin::Time => write!(f, "{}+T", color),
+                    WinMargin::Forfeit => write!(f, "{}+F", color),
+                    WinMargin::Unknown => write!(f, "{}+", color),
+                }
+            }
+        }
+    }
+}
+
+impl Goban {
+    /// Scores the position, treating the given stones as dead.
+    ///
+    /// Empty regions bordered only by one color's living stones are that color's territory.
+    pub fn score(&self, dead_stones: &[(u8, u8)], komi: f64, scoring_rule: ScoringRule) -> Score {
+        let stride = self.stride();
+        let mut dead = Bitboard::empty();
+        for &point in dead_stones {
+            if self.stone_at(point).is_some() {
+                dead.insert(self.index(point));
+            }
+        }
+        let black_alive = self.black_stones.and_not(&dead);
+        let white_alive = self.white_stones.and_not(&dead);
+        let empty = self.on_board.and_not(&black_alive.or(&white_alive));
+        let mut black_territory = Bitboard::empty();
+        let mut white_territory = Bitboard::empty();
+        for region in empty.components(stride) {
+            let border = region.dilate(stride).and(&self.on_board).and_not(&region);
+            if border.is_empty() {
+                continue;
+            } else if border.and_not(&black_alive).is_empty() {
+                black_territory = black_territory.or(&region);
+            } else if border.and_not(&white_alive).is_empty() {
+                white_territory = white_territory.or(&region);
+            }
+        }
+
+        let dead_black = self.black_stones.and(&dead).count() as f64;
+        let dead_white = self.white_stones.and(&dead).count() as f64;
+        let (black_points, white_points) = match scoring_rule {
+            ScoringRule::Area => (
+                (black_alive.count() + black_territory.count()) as f64,
+                (white_alive.count() + white_territory.count()) as f64,
+            ),
+            ScoringRule::Territory => (
+                black_territory.count() as f64 + self.black_captures as f64 + dead_white,
+                white_territory.count() as f64 + self.white_captures as f64 + dead_black,
+            ),
+        };
+
+        Score {
+            scoring_rule,
+            komi,
+            black_territory: self.points(&black_territory),
+            white_territory: self.points(&white_territory),
+            dead_stones: self
+                .stones()
+                .filter(|stone| dead.contains(self.index((stone.x, stone.y))))
+                .collect(),
+            black_points,
+            white_points: white_points + komi,
+        }
+    }
+
+    /// Returns the stones lying in the other player's recorded territory (as in SGF `TB`/`TW`).
+    pub fn dead_stones_in_territory(
+        &self,
+        black_territory: &[(u8, u8)],
+        white_territory: &[(u8, u8)],
+    ) -> Vec<(u8, u8)> {
+        let dead_white = black_territory
+            .iter()
+            .filter(|&&point| self.stone_at(point) == Some(StoneColor::White));
+        let dead_black = white_territory
+            .iter()
+            .filter(|&&point| self.stone_at(point) == Some(StoneColor::Black));
+
+        dead_white.chain(dead_black).cloned().collect()
+    }
+
+    /// Guesses which stones are dead in a finished game.
+    ///
+    /// Stones are considered dead if they sit in a small area walled off by the opponent's
+    /// unconditionally alive stones, too small to make two eyes in. This catches dead stones left
+    /// inside eyes and other small enclosures, but gives up on anything else: large territories
+    /// are rarely walled off by unconditionally alive stones, so stones in them are left alive.
+    pub fn estimate_dead_stones(&self) -> Vec<(u8, u8)> {
+        // The smallest area with two eyes is eight points, with both eyes on the edge.
+        const MAX_DEAD_AREA: usize = 7;
+
+        let stride = self.stride();
+        let mut dead = Bitboard::empty();
+        for &color in &[StoneColor::Black, StoneColor::White] {
+            let alive = self.unconditionally_alive(color);
+            let opponent_alive = self.unconditionally_alive(color.opponent());
+            let candidates = self.stones_of(color).and_not(&alive);
+            for area in self.on_board.and_not(&opponent_alive).components(stride) {
+                let walled_off = !area
+                    .dilate(stride)
+                    .and(&self.on_board)
+                    .and_not(&area)
+                    .is_empty();
+                if walled_off && area.count() <= MAX_DEAD_AREA && area.and(&alive).is_empty() {
+                    dead = dead.or(&area.and(&candidates));
+                }
+            }
+        }
+
+        self.points(&dead)
+    }
+
+    // Benson's algorithm for the stones of `color` which can't be captured even if the opponent
+    // always plays and `color` always passes.
+    fn unconditionally_alive(&self, color: StoneColor) -> Bitboard {
+        let stride = self.stride();
+        let own = *self.stones_of(color);
+        let empty = self.empty_points();
+        let mut chains = own.components(stride);
+        let mut regions = self.on_board.and_not(&own).components(stride);
+        loop {
+            let alive = chains
+                .iter()
+                .fold(Bitboard::empty(), |acc, chain| acc.or(chain));
+            let region_count = regions.len();
+            regions.retain(|region| region.dilate(stride).and(&own).and_not(&alive).is_empty());
+            let chain_count = chains.len();
+            chains.retain(|chain| {
+                // A region is vital to a chain if all its empty points are liberties of the chain.
+                let neighbors = chain.dilate(stride);
+                let vital_regions = regions
+                    .iter()
+                    .filter(|region| {
+                        !region.and(&neighbors).is_empty()
+                            && region.and(&empty).and_not(&neighbors).is_empty()
+                    })
+                    .count();
+                vital_regions >= 2
+            });
+            if chains.len() == chain_count && regions.len() == region_count {
+                return alive;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Builds a board from rows of `X` for black, `O` for white and `.` for empty points.
+    fn board(rows: &[&str]) -> Goban {
+        let mut goban = Goban::new((rows[0].len() as u8, rows.len() as u8));
+        for (y, row) in rows.iter().enumerate() {
+            for (x, c) in row.chars().enumerate() {
+                let color = match c {
+                    'X' => StoneColor::Black,
+                    'O' => StoneColor::White,
+                    _ => continue,
+                };
+                goban
+                    .add_stone(Stone::new(x as u8, y as u8, color))
+                    .unwrap();
+            }
+        }
+        goban
+    }
+
+    // White lives in the top rows with two eyes. Black's wall isn't unconditionally alive, but
+    // owns everything below it.
+    const SETTLED: [&str; 9] = [
+        "O.OOOOO.O",
+        "OOOOOOOOO",
+        "OOOOOOOOO",
+        "XXXXXXXXX",
+        ".........",
+        ".........",
+        ".........",
+        ".........",
+        ".........",
+    ];
+
+    // As above, but with a dead black stone inside one of white's eyes.
+    const DEAD_STONE: [&str; 9] = [
+        "O.X.OOO.O",
+        "OOOOOOOOO",
+        "OOOOOOOOO",
+        "XXXXXXXXX",
+        ".........",
+        ".........",
+        ".........",
+        ".........",
+        ".........",
+    ];
+
+    #[test]
+    fn settled_position_has_no_dead_stones() {
+        let goban = board(&SETTLED);
+        assert_eq!(goban.estimate_dead_stones(), vec![]);
+
+        let score = goban.score(&[], 0.0, ScoringRule::Area);
+        assert_eq!((score.black_points, score.white_points), (54.0, 27.0));
+        assert_eq!(
+            score.result(),
+            GameResult::Win(StoneColor::Black, WinMargin::Points(27.0))
+        );
+    }
+
+    #[test]
+    fn finds_dead_stones_inside_eyes() {
+        let goban = board(&DEAD_STONE);
+        let dead_stones = goban.estimate_dead_stones();
+        assert_eq!(dead_stones, vec![(2, 0)]);
+
+        let score = goban.score(&dead_stones, 6.5, ScoringRule::Area);
+        assert_eq!((score.black_points, score.white_points), (54.0, 33.5));
+        assert_eq!(score.dead_stones, vec![Stone::new(2, 0, StoneColor::Black)]);
+
+        let score = goban.score(&dead_stones, 6.5, ScoringRule::Territory);
+        assert_eq!((score.black_points, score.white_points), (45.0, 11.5));
+    }
+
+    #[test]
+    fn unsettled_position_has_no_dead_stones() {
+        let goban = board(&["..X..", ".OXO.", "..X..", ".....", "....."]);
+        assert_eq!(goban.estimate_dead_stones(), vec![]);
+    }
+}
@@ -0,0 +1,189 @@
+//! Support for SGF coordinates on boards larger than 26x26.
+//!
+//! SGF uses `a`-`z` for the first 26 lines of a board and `A`-`Z` for lines 27 to 52, but
+//! `sgf_parse` maps both cases to the first 26 lines. For games on large boards we re-read the
+//! raw point values of every property the UI plays from, and decode them ourselves.
+
+use std::collections::HashSet;
+
+use sgf_parse::{go, SgfNode, SgfParseError, SgfProp};
+
+const POINT_PROPS: [&str; 7] = ["B", "W", "AB", "AW", "AE", "TB", "TW"];
+
+// The identifier and raw values of each property in a node.
+type RawNode = Vec<(String, Vec<String>)>;
+
+/// Parses go games like `sgf_parse::go::parse`, with the full range of coordinates.
+pub fn parse(text: &str) -> Result<Vec<SgfNode<go::Prop>>, SgfParseError> {
+    let mut gametrees = go::parse(text)?;
+    if gametrees.iter().any(is_large_board) {
+        let raw_gametrees = raw_point_props(text);
+        for (gametree, raw_nodes) in gametrees.iter_mut().zip(raw_gametrees) {
+            if is_large_board(gametree) {
+                let mut raw_nodes = raw_nodes.into_iter();
+                fix_points(gametree, &mut raw_nodes);
+            }
+        }
+    }
+
+    Ok(gametrees)
+}
+
+fn is_large_board(root: &SgfNode<go::Prop>) -> bool {
+    match root.get_property("SZ") {
+        Some(go::Prop::SZ((width, height))) => *width > 26 || *height > 26,
+        _ => false,
+    }
+}
+
+// Replaces the point properties of the node and its descendents, in the same depth first order
+// they appear in the text.
+fn fix_points(node: &mut SgfNode<go::Prop>, raw_nodes: &mut impl Iterator<Item = RawNode>) {
+    let raw_props = raw_nodes.next().unwrap_or_default();
+    for (identifier, values) in raw_props {
+        let prop = match parse_point_prop(&identifier, &values) {
+            Some(prop) => prop,
+            None => continue,
+        };
+        for existing in node.properties.iter_mut() {
+            if existing.identifier() == identifier {
+                *existing = prop;
+                break;
+            }
+        }
+    }
+    for child in node.children.iter_mut() {
+        fix_points(child, raw_nodes);
+    }
+}
+
+fn parse_point_prop(identifier: &str, values: &[String]) -> Option<go::Prop> {
+    let prop = match identifier {
+        "B" => go::Prop::B(parse_move(values)?),
+        "W" => go::Prop::W(parse_move(values)?),
+        "AB" => go::Prop::AB(parse_point_list(values)?),
+        "AW" => go::Prop::AW(parse_point_list(values)?),
+        "AE" => go::Prop::AE(parse_point_list(values)?),
+        "TB" => go::Prop::TB(parse_point_list(values)?),
+        "TW" => go::Prop::TW(parse_point_list(values)?),
+        _ => return None,
+    };
+
+    Some(prop)
+}
+
+fn parse_move(values: &[String]) -> Option<go::Move> {
+    match values {
+        [value] if value.is_empty() => Some(go::Move::Pass),
+        [value] => Some(go::Move::Move(parse_point(value)?)),
+        _ => None,
+    }
+}
+
+// Parses a list of points, expanding any compressed `ul:lr` rectangles.
+fn parse_point_list(values: &[String]) -> Option<HashSet<go::Point>> {
+    let mut points = HashSet::new();
+    for value in values {
+        let mut corners = value.splitn(2, ':');
+        let upper_left = parse_point(corners.next()?)?;
+        let lower_right = match corners.next() {
+            Some(corner) => parse_point(corner)?,
+            None => upper_left,
+        };
+        for x in upper_left.x..=lower_right.x {
+            for y in upper_left.y..=lower_right.y {
+                points.insert(go::Point { x, y });
+            }
+        }
+    }
+
+    Some(points)
+}
+
+fn parse_point(value: &str) -> Option<go::Point> {
+    fn coordinate(c: u8) -> Option<u8> {
+        match c {
+            b'a'..=b'z' => Some(c - b'a'),
+            b'A'..=b'Z' => Some(c - b'A' + 26),
+            _ => None,
+        }
+    }
+    match value.as_bytes() {
+        &[x, y] => Some(go::Point {
+            x: coordinate(x)?,
+            y: coordinate(y)?,
+        }),
+        _ => None,
+    }
+}
+
+// Returns the point properties of each node of each gametree, with nodes in the order they
+// appear in the text. The text is assumed to have already been parsed successfully.
+fn raw_point_props(text: &str) -> Vec<Vec<RawNode>> {
+    let mut gametrees: Vec<Vec<RawNode>> = vec![];
+    let mut depth = 0;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '(' => {
+                if depth == 0 {
+                    gametrees.push(vec![]);
+                }
+                depth += 1;
+            }
+            ')' => depth -= 1,
+            ';' => {
+                if let Some(nodes) = gametrees.last_mut() {
+                    nodes.push(vec![]);
+                }
+            }
+            c if c.is_ascii_alphabetic() => {
+                // Older SGF versions allow lower case letters in identifiers, which are ignored.
+                let mut identifier: String = std::iter::once(c).collect();
+                while let Some(&c) = chars.peek() {
+                    if c == '[' {
+                        break;
+                    }
+                    identifier.push(c);
+                    chars.next();
+                }
+                let identifier: String = identifier
+                    .chars()
+                    .filter(|c| c.is_ascii_uppercase())
+                    .collect();
+                let mut values = vec![];
+                loop {
+                    while chars.peek().is_some_and(|c| c.is_ascii_whitespace()) {
+                        chars.next();
+                    }
+                    if chars.peek() != Some(&'[') {
+                        break;
+                    }
+                    chars.next();
+                    let mut value = String::new();
+                    let mut escaped = false;
+                    for c in chars.by_ref() {
+                        match c {
+                            ']' if !escaped => break,
+                            '\\' if !escaped => escaped = true,
+                            c => {
+                                escaped = false;
+                                value.push(c);
+                            }
+                        }
+                    }
+                    values.push(value);
+                }
+                let node = gametrees.last_mut().and_then(|nodes| nodes.last_mut());
+                if let Some(props) = node {
+                    if POINT_PROPS.contains(&identifier.as_str()) {
+                        props.push((identifier, values));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    gametrees
+}
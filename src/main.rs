@@ -1,13 +1,21 @@
 extern crate gl;
 extern crate glutin;
+extern crate goban_screenhack;
 extern crate nanovg;
 extern crate sgf_parse;
 
+mod archive;
 mod args;
-mod goban;
+mod catalog;
+mod formats;
+mod large_boards;
+mod scan;
 mod ui;
+mod xdg;
 mod xscreensaver_context;
 
+use goban_screenhack::goban;
+
 fn main() {
     // Parse arguments
     let args: Vec<String> = std::env::args().collect();
@@ -49,21 +57,37 @@ fn main() {
         }
     };
 
+    if !load_font(&nanovg_context) {
+        eprintln!("No usable font found. Game results will not be displayed.");
+    }
+
     // Goban setup
-    let sgfs = match load_sgfs(&parsed_args.sgf_dirs) {
-        Ok(sgfs) => sgfs,
-        Err(error) => {
-            eprintln!("{}", error);
-            std::process::exit(1);
-        }
+    let games = spawn_indexer(parsed_args.sgf_dirs, parsed_args.scan_options);
+    // Seeded runs ignore the recently shown games, so they can be reproduced.
+    let recent_games = match parsed_args.seed {
+        Some(_) => ui::RecentGames::disabled(),
+        None => ui::RecentGames::load(ui::RecentGames::default_path(), parsed_args.avoid_recent),
     };
-    let mut ui = match ui::UI::new(sgfs, parsed_args.move_delay, parsed_args.end_delay) {
-        Ok(ui) => ui,
+    let sgf_walker = match ui::SgfWalker::new(
+        games,
+        parsed_args.variation_policy,
+        parsed_args.game_order,
+        parsed_args.seed,
+        recent_games,
+    ) {
+        Ok(sgf_walker) => sgf_walker,
         Err(error) => {
             eprintln!("{}", error);
             std::process::exit(1);
         }
     };
+    let mut ui = ui::UI::new(
+        sgf_walker,
+        parsed_args.move_delay,
+        parsed_args.end_delay,
+        parsed_args.reverse,
+        parsed_args.show_influence,
+    );
 
     // Main Loop
     event_loop.run(move |event, _, control_flow| {
@@ -107,26 +131,54 @@ fn main() {
     });
 }
 
-fn load_sgfs(
-    sgf_dirs: &[std::path::PathBuf],
-) -> Result<Vec<sgf_parse::SgfNode<sgf_parse::go::Prop>>, Box<dyn std::error::Error>> {
-    let mut sgfs = vec![];
-    for dir in sgf_dirs.iter() {
-        for entry in std::fs::read_dir(&dir)? {
-            let path = entry?.path();
-            if path.is_file() {
-                if let Some("sgf") = path.extension().and_then(std::ffi::OsStr::to_str) {
-                    let contents = std::fs::read_to_string(path.clone())?;
-                    match sgf_parse::go::parse(&contents) {
-                        Ok(new_nodes) => sgfs.extend(new_nodes),
-                        Err(e) => eprintln!("Error parsing {}: {}", path.to_string_lossy(), e),
-                    }
-                }
+// Finds games on a background thread, sending each file's games as soon as it's indexed. Only
+// files which have changed since the catalog was last cached get parsed. The channel closes once
+// the scan is finished.
+fn spawn_indexer(
+    sgf_dirs: Vec<std::path::PathBuf>,
+    scan_options: scan::ScanOptions,
+) -> std::sync::mpsc::Receiver<Vec<catalog::CatalogEntry>> {
+    // Save progress now and then, in case the screensaver is stopped part way through a scan.
+    const SAVE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(30);
+
+    let (sender, receiver) = std::sync::mpsc::channel();
+    std::thread::spawn(move || {
+        let cache_path = catalog::Catalog::default_path();
+        let mut catalog = catalog::Catalog::load(&cache_path);
+        let mut last_save = std::time::Instant::now();
+        for path in scan::find_files(&sgf_dirs, &scan_options) {
+            let entries = catalog.add_file(&path);
+            if !entries.is_empty() && sender.send(entries).is_err() {
+                return;
+            }
+            if last_save.elapsed() > SAVE_INTERVAL {
+                save_catalog(&catalog, &cache_path);
+                last_save = std::time::Instant::now();
             }
         }
+        catalog.forget_missing();
+        save_catalog(&catalog, &cache_path);
+    });
+
+    receiver
+}
+
+fn save_catalog(catalog: &catalog::Catalog, path: &std::path::Path) {
+    if let Err(error) = catalog.save(path) {
+        eprintln!("Failed to save game catalog: {}", error);
     }
+}
 
-    Ok(sgfs)
+fn load_font(context: &nanovg::Context) -> bool {
+    const FONT_PATHS: [&str; 4] = [
+        "/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf",
+        "/usr/share/fonts/TTF/DejaVuSans.ttf",
+        "/usr/share/fonts/truetype/liberation/LiberationSans-Regular.ttf",
+        "/usr/share/fonts/truetype/freefont/FreeSans.ttf",
+    ];
+    FONT_PATHS
+        .iter()
+        .any(|path| nanovg::Font::from_file(context, ui::FONT_NAME, path).is_ok())
 }
 
 fn get_geometry(
@@ -0,0 +1,169 @@
+//! Finding game files in the sgf directories.
+
+use std::path::{Path, PathBuf};
+
+use walkdir::WalkDir;
+
+pub const DEFAULT_INCLUDE: [&str; 10] = [
+    "*.sgf", "*.sgf.gz", "*.gib", "*.ngf", "*.ugf", "*.ugi", "*.zip", "*.tar", "*.tar.gz", "*.tgz",
+];
+
+#[derive(Debug)]
+pub struct ScanOptions {
+    /// How many levels of directories to descend into. The top level is depth 1.
+    pub max_depth: usize,
+    /// Glob patterns for files to load. Patterns without a `/` match against file names, and
+    /// others against the path relative to the sgf directory. Only `*`, `**` and `?` are special;
+    /// there are no character classes, so `[ab]` only matches itself.
+    pub include: Vec<String>,
+    /// Glob patterns for files and directories to skip.
+    pub exclude: Vec<String>,
+}
+
+/// Returns every matching file under the directories, as they're found.
+///
+/// Each directory's entries are visited sorted by name. Symlinks are followed, but never back
+/// into a directory already being scanned. Entries which can't be read are reported and skipped.
+pub fn find_files<'a>(
+    dirs: &'a [PathBuf],
+    options: &'a ScanOptions,
+) -> impl Iterator<Item = PathBuf> + 'a {
+    dirs.iter().flat_map(move |dir| {
+        WalkDir::new(dir)
+            .min_depth(1)
+            .max_depth(options.max_depth)
+            .follow_links(true)
+            .sort_by(|a, b| a.file_name().cmp(b.file_name()))
+            .into_iter()
+            .filter_entry(move |entry| {
+                let relative_path = entry.path().strip_prefix(dir).unwrap_or(entry.path());
+                !is_match(&options.exclude, relative_path)
+            })
+            .filter_map(move |entry| match entry {
+                Ok(entry) => Some(entry),
+                Err(error) => {
+                    eprintln!("Error scanning {}: {}", dir.to_string_lossy(), error);
+                    None
+                }
+            })
+            .filter(move |entry| {
+                let relative_path = entry.path().strip_prefix(dir).unwrap_or(entry.path());
+                entry.file_type().is_file() && is_match(&options.include, relative_path)
+            })
+            .map(|entry| entry.into_path())
+    })
+}
+
+fn is_match(patterns: &[String], relative_path: &Path) -> bool {
+    let path = relative_path.to_string_lossy();
+    let file_name = path.rsplit('/').next().unwrap_or(&path);
+    patterns.iter().any(|pattern| {
+        if pattern.contains('/') {
+            glob_match(pattern.as_bytes(), path.as_bytes())
+        } else {
+            glob_match(pattern.as_bytes(), file_name.as_bytes())
+        }
+    })
+}
+
+// Matches shell style globs, where `*` and `?` don't match `/`, but `**` matches anything.
+// Character classes and escapes aren't supported, so `[`, `]` and `\` match themselves.
+fn glob_match(pattern: &[u8], text: &[u8]) -> bool {
+    match pattern {
+        [] => text.is_empty(),
+        [b'*', b'*', b'/', rest @ ..] => (0..=text.len())
+            .filter(|&i| i == 0 || text[i - 1] == b'/')
+            .any(|i| glob_match(rest, &text[i..])),
+        [b'*', b'*', rest @ ..] => (0..=text.len()).any(|i| glob_match(rest, &text[i..])),
+        [b'*', rest @ ..] => (0..=text.len())
+            .take_while(|&i| i == 0 || text[i - 1] != b'/')
+            .any(|i| glob_match(rest, &text[i..])),
+        [b'?', rest @ ..] => match text {
+            [c, text @ ..] if *c != b'/' => glob_match(rest, text),
+            _ => false,
+        },
+        [p, rest @ ..] => match text {
+            [c, text @ ..] if c == p => glob_match(rest, text),
+            _ => false,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn patterns(patterns: &[&str]) -> Vec<String> {
+        patterns.iter().map(|pattern| pattern.to_string()).collect()
+    }
+
+    #[test]
+    fn glob_match_table() {
+        let cases = [
+            ("*.sgf", "game.sgf", true),
+            ("*.sgf", "game.sgf.gz", false),
+            ("*.sgf", "pros/game.sgf", false),
+            ("pros/*.sgf", "pros/game.sgf", true),
+            ("pros/*.sgf", "pros/1990/game.sgf", false),
+            ("**/x/*.sgf", "x/game.sgf", true),
+            ("**/x/*.sgf", "a/b/x/game.sgf", true),
+            ("**/x/*.sgf", "a/xx/game.sgf", false),
+            ("**/x/*.sgf", "x/a/game.sgf", false),
+            ("**/*.sgf", "a/b/game.sgf", true),
+            ("a/**", "a/b/c", true),
+            ("game?.sgf", "game1.sgf", true),
+            ("game?.sgf", "game.sgf", false),
+            ("a?b", "a/b", false),
+            ("*", "", true),
+            ("", "", true),
+            ("", "a", false),
+            ("[ab].sgf", "a.sgf", false),
+            ("[ab].sgf", "[ab].sgf", true),
+        ];
+        for (pattern, text, expected) in cases {
+            assert_eq!(
+                glob_match(pattern.as_bytes(), text.as_bytes()),
+                expected,
+                "{:?} against {:?}",
+                pattern,
+                text
+            );
+        }
+    }
+
+    #[test]
+    fn patterns_without_slash_match_file_names() {
+        let include = patterns(&["*.sgf"]);
+        assert!(is_match(&include, Path::new("game.sgf")));
+        assert!(is_match(&include, Path::new("pros/1990/game.sgf")));
+        assert!(!is_match(&include, Path::new("pros/1990/game.gib")));
+
+        let include = patterns(&["pros/*.sgf"]);
+        assert!(is_match(&include, Path::new("pros/game.sgf")));
+        assert!(!is_match(&include, Path::new("amateurs/pros/game.sgf")));
+    }
+
+    #[test]
+    fn exclude_patterns_skip_directories() {
+        let dir =
+            std::env::temp_dir().join(format!("goban-screenhack-scan-{}", std::process::id()));
+        for subdir in ["keep", "old", "keep/old", "keep/older"] {
+            std::fs::create_dir_all(dir.join(subdir)).unwrap();
+            std::fs::write(dir.join(subdir).join("game.sgf"), "(;)").unwrap();
+        }
+        let options = ScanOptions {
+            max_depth: 10,
+            include: patterns(&["*.sgf"]),
+            exclude: patterns(&["old"]),
+        };
+        let dirs = [dir.clone()];
+        let found: Vec<_> = find_files(&dirs, &options)
+            .map(|path| path.strip_prefix(&dir).unwrap().to_path_buf())
+            .collect();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(
+            found,
+            [Path::new("keep/game.sgf"), Path::new("keep/older/game.sgf")]
+        );
+    }
+}
@@ -0,0 +1,284 @@
+use super::recent_games::RecentGames;
+use crate::catalog::CatalogEntry;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+/// How to pick the next game to play.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum GameOrder {
+    /// Pick any game at random, independently each time.
+    Random,
+    /// Play every game once in a random order before repeating any.
+    Shuffle,
+    /// Play games in order of their paths.
+    Sequential,
+}
+
+/// The pool of games to show, and which one to show next.
+pub struct GamePicker {
+    games: Vec<CatalogEntry>,
+    game_order: GameOrder,
+    // Games still to be shown before any repeats in shuffle mode.
+    shuffle_bag: Vec<usize>,
+    game_index: Option<usize>,
+    // Sequential play picks up after this game, once it's been indexed.
+    resume_after: Option<String>,
+}
+
+impl GamePicker {
+    pub fn new(game_order: GameOrder, recent_games: &RecentGames) -> GamePicker {
+        GamePicker {
+            games: vec![],
+            game_order,
+            shuffle_bag: vec![],
+            game_index: None,
+            resume_after: match game_order {
+                GameOrder::Sequential => recent_games.last().map(String::from),
+                _ => None,
+            },
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.games.len()
+    }
+
+    /// Adds a game to the pool. Games are kept in order of their paths whatever order they're
+    /// added in, so neither sequential play nor a seeded run depends on how they were indexed.
+    pub fn add_game(&mut self, game: CatalogEntry, rng: &mut StdRng) {
+        let index = self
+            .games
+            .partition_point(|other| order_key(other) <= order_key(&game));
+        // Games mostly arrive in order, so there's rarely anything to renumber.
+        if index < self.games.len() {
+            for i in self.shuffle_bag.iter_mut().chain(&mut self.game_index) {
+                if *i >= index {
+                    *i += 1;
+                }
+            }
+        }
+        if self.resume_after.as_deref() == Some(&game.source) {
+            self.game_index = Some(index);
+            self.resume_after = None;
+        }
+        // Slot new games into the current pass at random, rather than saving them for the next.
+        if !self.shuffle_bag.is_empty() {
+            let position = rng.gen_range(0..=self.shuffle_bag.len());
+            self.shuffle_bag.insert(position, index);
+        }
+        self.games.insert(index, game);
+    }
+
+    /// Picks the next game to show.
+    ///
+    /// Panics if there are no games.
+    pub fn next_game(&mut self, recent_games: &RecentGames, rng: &mut StdRng) -> &CatalogEntry {
+        let game_index = self.next_game_index(recent_games, rng);
+        self.game_index = Some(game_index);
+
+        &self.games[game_index]
+    }
+
+    fn next_game_index(&mut self, recent_games: &RecentGames, rng: &mut StdRng) -> usize {
+        match self.game_order {
+            GameOrder::Random => {
+                let candidates: Vec<_> = (0..self.games.len())
+                    .filter(|&i| !recent_games.contains(&self.games[i].source))
+                    .collect();
+                match candidates.choose(rng) {
+                    Some(&index) => index,
+                    None => rng.gen_range(0..self.games.len()),
+                }
+            }
+            GameOrder::Sequential => self
+                .game_index
+                .map_or(0, |index| (index + 1) % self.games.len()),
+            GameOrder::Shuffle => {
+                if self.shuffle_bag.is_empty() {
+                    self.shuffle_bag = (0..self.games.len()).collect();
+                    self.shuffle_bag.shuffle(rng);
+                    // Don't repeat the last game of one pass as the first of the next.
+                    let last = self.shuffle_bag.len() - 1;
+                    if last > 0 && Some(self.shuffle_bag[last]) == self.game_index {
+                        self.shuffle_bag.swap(0, last);
+                    }
+                    // Games are taken from the end, so move recently shown ones to the front.
+                    let games = &self.games;
+                    self.shuffle_bag
+                        .sort_by_key(|&i| !recent_games.contains(&games[i].source));
+                }
+                self.shuffle_bag.pop().unwrap() // The bag was just refilled if it was empty.
+            }
+        }
+    }
+}
+
+fn order_key(game: &CatalogEntry) -> (&std::path::Path, Option<&str>, usize) {
+    (&game.path, game.member.as_deref(), game.offset)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+
+    fn game(source: &str) -> CatalogEntry {
+        CatalogEntry {
+            source: source.to_string(),
+            path: source.into(),
+            member: None,
+            offset: 0,
+            length: 0,
+            board_size: (19, 19),
+            black_player: String::new(),
+            white_player: String::new(),
+        }
+    }
+
+    fn picker(game_order: GameOrder, count: usize, recent_games: &RecentGames) -> GamePicker {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut picker = GamePicker::new(game_order, recent_games);
+        for i in 0..count {
+            picker.add_game(game(&i.to_string()), &mut rng);
+        }
+        picker
+    }
+
+    fn picks(
+        picker: &mut GamePicker,
+        recent_games: &RecentGames,
+        seed: u64,
+        n: usize,
+    ) -> Vec<String> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        (0..n)
+            .map(|_| picker.next_game(recent_games, &mut rng).source.clone())
+            .collect()
+    }
+
+    // Recent games as if the given games had been shown in a previous run.
+    fn recent(name: &str, ids: &[&str]) -> RecentGames {
+        let path =
+            std::env::temp_dir().join(format!("goban-screenhack-{}-{}", name, std::process::id()));
+        std::fs::write(&path, ids.join("\n")).unwrap();
+        let recent_games = RecentGames::load(path.clone(), ids.len());
+        std::fs::remove_file(path).unwrap();
+        recent_games
+    }
+
+    #[test]
+    fn same_seed_picks_same_games() {
+        let none = RecentGames::disabled();
+        for game_order in [GameOrder::Random, GameOrder::Shuffle] {
+            let first = picks(&mut picker(game_order, 20, &none), &none, 7, 30);
+            let second = picks(&mut picker(game_order, 20, &none), &none, 7, 30);
+            let other = picks(&mut picker(game_order, 20, &none), &none, 8, 30);
+            assert_eq!(first, second);
+            assert_ne!(first, other);
+        }
+    }
+
+    #[test]
+    fn shuffle_plays_every_game_once_per_pass() {
+        let none = RecentGames::disabled();
+        let mut picker = picker(GameOrder::Shuffle, 10, &none);
+        let picked = picks(&mut picker, &none, 3, 30);
+        for pass in picked.chunks(10) {
+            let mut pass = pass.to_vec();
+            pass.sort();
+            pass.dedup();
+            assert_eq!(pass.len(), 10);
+        }
+        assert!(picked.windows(2).all(|pair| pair[0] != pair[1]));
+    }
+
+    #[test]
+    fn shuffle_plays_recent_games_last() {
+        let recent_games = recent("shuffle", &["2", "5"]);
+        let mut picker = picker(GameOrder::Shuffle, 6, &recent_games);
+        let mut last = picks(&mut picker, &recent_games, 1, 6).split_off(4);
+        last.sort();
+        assert_eq!(last, ["2", "5"]);
+    }
+
+    #[test]
+    fn random_avoids_recent_games() {
+        let recent_games = recent("random", &["0", "1", "2"]);
+        let mut picker = picker(GameOrder::Random, 4, &recent_games);
+        assert!(picks(&mut picker, &recent_games, 1, 10)
+            .iter()
+            .all(|game| game == "3"));
+    }
+
+    #[test]
+    fn games_are_kept_in_path_order() {
+        let none = RecentGames::disabled();
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut in_order = GamePicker::new(GameOrder::Sequential, &none);
+        let mut out_of_order = GamePicker::new(GameOrder::Sequential, &none);
+        for i in 0..6 {
+            in_order.add_game(game(&i.to_string()), &mut rng);
+        }
+        for i in [3, 0, 5, 1, 4, 2] {
+            out_of_order.add_game(game(&i.to_string()), &mut rng);
+        }
+        let expected = ["0", "1", "2", "3", "4", "5", "0"];
+        assert_eq!(picks(&mut in_order, &none, 0, 7), expected);
+        assert_eq!(picks(&mut out_of_order, &none, 0, 7), expected);
+    }
+
+    #[test]
+    fn sequential_continues_in_order_as_games_arrive() {
+        let none = RecentGames::disabled();
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut picker = GamePicker::new(GameOrder::Sequential, &none);
+        picker.add_game(game("2"), &mut rng);
+        assert_eq!(picks(&mut picker, &none, 0, 1), ["2"]);
+        picker.add_game(game("0"), &mut rng);
+        picker.add_game(game("3"), &mut rng);
+        assert_eq!(picks(&mut picker, &none, 0, 3), ["3", "0", "2"]);
+    }
+
+    #[test]
+    fn games_added_mid_pass_join_it() {
+        let none = RecentGames::disabled();
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut picker = GamePicker::new(GameOrder::Shuffle, &none);
+        for i in [1, 3, 5, 7] {
+            picker.add_game(game(&i.to_string()), &mut rng);
+        }
+        let mut picked = picks(&mut picker, &none, 2, 2);
+        for i in [0, 2, 4, 6] {
+            picker.add_game(game(&i.to_string()), &mut rng);
+        }
+        picked.extend(picks(&mut picker, &none, 2, 6));
+        picked.sort();
+        assert_eq!(picked, ["0", "1", "2", "3", "4", "5", "6", "7"]);
+    }
+
+    #[test]
+    fn seeded_shuffle_ignores_arrival_order() {
+        let none = RecentGames::disabled();
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut reversed = GamePicker::new(GameOrder::Shuffle, &none);
+        for i in (0..20).rev() {
+            reversed.add_game(game(&format!("{:02}", i)), &mut rng);
+        }
+        let mut in_order = GamePicker::new(GameOrder::Shuffle, &none);
+        for i in 0..20 {
+            in_order.add_game(game(&format!("{:02}", i)), &mut rng);
+        }
+        assert_eq!(
+            picks(&mut reversed, &none, 9, 20),
+            picks(&mut in_order, &none, 9, 20)
+        );
+    }
+
+    #[test]
+    fn sequential_resumes_after_last_game_shown() {
+        let recent_games = recent("sequential", &["0", "2"]);
+        let mut picker = picker(GameOrder::Sequential, 4, &recent_games);
+        assert_eq!(picks(&mut picker, &recent_games, 0, 3), ["3", "0", "1"]);
+    }
+}
@@ -1,7 +1,14 @@
-use super::{Goban, Stone, StoneColor};
+use super::{Goban, Influence, Stone, StoneColor};
+
+pub const FONT_NAME: &str = "sans";
 
 pub struct GobanDisplay<'a> {
     goban: &'a Goban,
+    black_territory: &'a [(u8, u8)],
+    white_territory: &'a [(u8, u8)],
+    dead_stones: &'a [(u8, u8)],
+    influence: Option<&'a Influence>,
+    info_lines: &'a [String],
 }
 
 impl<'a> GobanDisplay<'a> {
@@ -10,30 +17,54 @@ impl<'a> GobanDisplay<'a> {
     const BORDER_WIDTH: f32 = 1.0 / 11.0;
     const BOARD_MARGIN: f32 = 14.1 / 22.0;
     const HOSHI_RADIUS: f32 = 1.0 / 11.0;
-    const DEFAULT_HOSHIS: [(u8, u8); 0] = [];
-    const NINE_HOSHIS: [(u8, u8); 4] = [(2, 2), (2, 6), (6, 2), (6, 6)];
-    const THIRTEEN_HOSHIS: [(u8, u8); 5] = [(3, 3), (3, 9), (6, 6), (9, 3), (9, 9)];
-    const NINETEEN_HOSHIS: [(u8, u8); 9] = [
-        (3, 3),
-        (3, 9),
-        (3, 15),
-        (9, 3),
-        (9, 9),
-        (9, 15),
-        (15, 3),
-        (15, 9),
-        (15, 15),
-    ];
+    const TERRITORY_MARKER_SIZE: f32 = 0.3;
+    const DEAD_STONE_ALPHA: f32 = 0.45;
+    const INFLUENCE_ALPHA: f32 = 0.35;
+    const INFO_TEXT_SCALE: f32 = 1.0 / 30.0;
 
     pub fn new(goban: &'a Goban) -> GobanDisplay<'a> {
-        GobanDisplay { goban }
+        GobanDisplay {
+            goban,
+            black_territory: &[],
+            white_territory: &[],
+            dead_stones: &[],
+            influence: None,
+            info_lines: &[],
+        }
+    }
+
+    pub fn with_territory(
+        mut self,
+        black_territory: &'a [(u8, u8)],
+        white_territory: &'a [(u8, u8)],
+    ) -> GobanDisplay<'a> {
+        self.black_territory = black_territory;
+        self.white_territory = white_territory;
+        self
+    }
+
+    pub fn with_dead_stones(mut self, dead_stones: &'a [(u8, u8)]) -> GobanDisplay<'a> {
+        self.dead_stones = dead_stones;
+        self
+    }
+
+    pub fn with_influence(mut self, influence: &'a Influence) -> GobanDisplay<'a> {
+        self.influence = Some(influence);
+        self
+    }
+
+    pub fn with_info_lines(mut self, info_lines: &'a [String]) -> GobanDisplay<'a> {
+        self.info_lines = info_lines;
+        self
     }
 
     pub fn draw(&self, frame: &mut nanovg::Frame, window_width: f32, window_height: f32) {
         // Transform to a frame where the spacing between lines is 1.0 and the origin is in the
         // upper right corner of the board drawing area.
-        let board_width = (self.goban.size.0 - 1) as f32 + 2.0 * GobanDisplay::BOARD_MARGIN;
-        let board_height = (self.goban.size.1 - 1) as f32 + 2.0 * GobanDisplay::BOARD_MARGIN;
+        let board_width =
+            self.goban.size.0.saturating_sub(1) as f32 + 2.0 * GobanDisplay::BOARD_MARGIN;
+        let board_height =
+            self.goban.size.1.saturating_sub(1) as f32 + 2.0 * GobanDisplay::BOARD_MARGIN;
         let aspect_ratio = board_width / board_height;
         let transform = if window_width > window_height * aspect_ratio {
             let scale = window_height / board_height;
@@ -69,21 +100,50 @@ impl<'a> GobanDisplay<'a> {
         // Now move the origin to (0, 0), and draw the lines and stones.
         let transform = transform.translate(GobanDisplay::BOARD_MARGIN, GobanDisplay::BOARD_MARGIN);
         frame.transformed(transform, |frame| self.draw_board(&frame));
+
+        self.draw_info(frame, window_height);
+    }
+
+    // Draw any info text in the upper left corner of the window.
+    fn draw_info(&self, frame: &nanovg::Frame, window_height: f32) {
+        let font = match nanovg::Font::find(frame.context(), FONT_NAME) {
+            Ok(font) => font,
+            Err(_) => return,
+        };
+        let text_size = window_height * GobanDisplay::INFO_TEXT_SCALE;
+        for (i, line) in self.info_lines.iter().enumerate() {
+            let position = (
+                text_size / 2.0,
+                text_size / 2.0 + i as f32 * text_size * 1.2,
+            );
+            frame.text(
+                font,
+                position,
+                line,
+                nanovg::TextOptions {
+                    size: text_size,
+                    color: nanovg::Color::new(1.0, 1.0, 1.0, 1.0),
+                    align: nanovg::Alignment::new().left().top(),
+                    ..Default::default()
+                },
+            );
+        }
     }
 
     // Draw the board, assuming the spacing between lines is 1.0.
     fn draw_board(&self, frame: &nanovg::Frame) {
         let line_color: nanovg::Color = nanovg::Color::new(0.0, 0.0, 0.0, 1.0);
+        let scale = self.detail_scale();
         let border_options = nanovg::StrokeOptions {
-            width: GobanDisplay::BORDER_WIDTH,
+            width: GobanDisplay::BORDER_WIDTH * scale,
             ..Default::default()
         };
         let line_options = nanovg::StrokeOptions {
-            width: GobanDisplay::LINE_WIDTH,
+            width: GobanDisplay::LINE_WIDTH * scale,
             ..Default::default()
         };
-        let width = (self.goban.size.0 - 1) as u32 as f32;
-        let height = (self.goban.size.1 - 1) as u32 as f32;
+        let width = self.goban.size.0.saturating_sub(1) as f32;
+        let height = self.goban.size.1.saturating_sub(1) as f32;
         frame.path(
             |path| {
                 path.rect((0.0, 0.0), (width, height));
@@ -93,11 +153,11 @@ impl<'a> GobanDisplay<'a> {
         );
         frame.path(
             |path| {
-                for i in 1..self.goban.size.0 as u32 - 1 {
+                for i in 1..self.goban.size.0.saturating_sub(1) {
                     path.move_to((i as f32, 0.0));
                     path.line_to((i as f32, height));
                 }
-                for i in 1..self.goban.size.1 as u32 - 1 {
+                for i in 1..self.goban.size.1.saturating_sub(1) {
                     path.move_to((0.0, i as f32));
                     path.line_to((width, i as f32));
                 }
@@ -106,10 +166,10 @@ impl<'a> GobanDisplay<'a> {
             Default::default(),
         );
 
-        for &(x, y) in self.hoshi_points() {
+        for (x, y) in self.hoshi_points() {
             frame.path(
                 |path| {
-                    path.circle((0.0, 0.0), GobanDisplay::HOSHI_RADIUS);
+                    path.circle((0.0, 0.0), GobanDisplay::HOSHI_RADIUS * scale);
                     path.fill(line_color, Default::default());
                 },
                 nanovg::PathOptions {
@@ -119,19 +179,88 @@ impl<'a> GobanDisplay<'a> {
             );
         }
 
+        if let Some(influence) = self.influence {
+            self.draw_influence(frame, influence);
+        }
+
         for stone in self.goban.stones() {
-            self.draw_stone(&frame, stone);
+            let alpha = if self.dead_stones.contains(&(stone.x, stone.y)) {
+                GobanDisplay::DEAD_STONE_ALPHA
+            } else {
+                1.0
+            };
+            self.draw_stone(frame, stone, alpha);
+        }
+
+        // Territory markers go on empty points only. Dead stones are already dimmed.
+        for &(x, y) in self.black_territory {
+            if self.goban.stone_at((x, y)).is_none() {
+                self.draw_territory_marker(frame, x, y, StoneColor::Black);
+            }
+        }
+        for &(x, y) in self.white_territory {
+            if self.goban.stone_at((x, y)).is_none() {
+                self.draw_territory_marker(frame, x, y, StoneColor::White);
+            }
+        }
+    }
+
+    // Shade the area each player controls, with a square centered on each point.
+    fn draw_influence(&self, frame: &nanovg::Frame, influence: &Influence) {
+        for &color in &[StoneColor::Black, StoneColor::White] {
+            let fill_color = match color {
+                StoneColor::Black => nanovg::Color::new(0.0, 0.0, 0.0, 1.0),
+                StoneColor::White => nanovg::Color::new(1.0, 1.0, 1.0, 1.0),
+            };
+            frame.path(
+                |path| {
+                    for x in 0..self.goban.size.0 {
+                        for y in 0..self.goban.size.1 {
+                            if influence.owner((x, y)) == Some(color) {
+                                path.rect((x as f32 - 0.5, y as f32 - 0.5), (1.0, 1.0));
+                            }
+                        }
+                    }
+                    path.fill(fill_color, Default::default());
+                },
+                nanovg::PathOptions {
+                    alpha: GobanDisplay::INFLUENCE_ALPHA,
+                    ..Default::default()
+                },
+            );
         }
     }
 
+    // Draw a small square marking a point as territory for the given color.
+    fn draw_territory_marker(&self, frame: &nanovg::Frame, x: u8, y: u8, color: StoneColor) {
+        let fill_color = match color {
+            StoneColor::Black => nanovg::Color::new(0.0, 0.0, 0.0, 1.0),
+            StoneColor::White => nanovg::Color::new(1.0, 1.0, 1.0, 1.0),
+        };
+        let size = GobanDisplay::TERRITORY_MARKER_SIZE;
+        frame.path(
+            |path| {
+                path.rect((-size / 2.0, -size / 2.0), (size, size));
+                path.fill(fill_color, Default::default());
+            },
+            nanovg::PathOptions {
+                transform: Some(self.point_frame_transform(x, y)),
+                ..Default::default()
+            },
+        );
+    }
+
     // Draw a stone centered at 0.0 assuming interline spacing of 1.0.
-    fn draw_stone(&self, frame: &nanovg::Frame, stone: Stone) {
+    fn draw_stone(&self, frame: &nanovg::Frame, stone: Stone, alpha: f32) {
+        // Keep the shadow from smudging the gaps between stones on crowded boards.
+        let shadow_offset = 0.025 / self.detail_scale();
         frame.path(
             |path| {
-                path.circle((0.025, 0.025), 0.475);
+                path.circle((shadow_offset, shadow_offset), 0.475);
                 path.fill(nanovg::Color::new(0.0, 0.0, 0.0, 0.5), Default::default());
             },
             nanovg::PathOptions {
+                alpha,
                 transform: Some(self.point_frame_transform(stone.x, stone.y)),
                 ..Default::default()
             },
@@ -158,22 +287,58 @@ impl<'a> GobanDisplay<'a> {
                 path.fill(paint, Default::default());
             },
             nanovg::PathOptions {
+                alpha,
                 transform: Some(self.point_frame_transform(stone.x, stone.y)),
                 ..Default::default()
             },
         );
     }
 
+    // Lines and star points are sized relative to the spacing between lines, which makes them
+    // hard to see on big boards. This thickens them for anything larger than 19x19.
+    fn detail_scale(&self) -> f32 {
+        let size = self.goban.size.0.max(self.goban.size.1) as f32;
+        (size / 19.0).max(1.0).sqrt()
+    }
+
     fn point_frame_transform(&self, x: u8, y: u8) -> nanovg::Transform {
         nanovg::Transform::new().with_translation(x as f32, y as f32)
     }
 
-    fn hoshi_points(&self) -> impl Iterator<Item = &(u8, u8)> {
-        match self.goban.size {
-            (9, 9) => GobanDisplay::NINE_HOSHIS.iter(),
-            (13, 13) => GobanDisplay::THIRTEEN_HOSHIS.iter(),
-            (19, 19) => GobanDisplay::NINETEEN_HOSHIS.iter(),
-            _ => GobanDisplay::DEFAULT_HOSHIS.iter(),
+    // Star points go on the 3-3 points of small boards and the 4-4 points of larger ones. Odd
+    // boards of 11 lines or more also get a center point, and sides of 15 lines or more get a
+    // star point halfway between the corners.
+    fn hoshi_points(&self) -> Vec<(u8, u8)> {
+        let (width, height) = self.goban.size;
+        let (xs, ys) = (hoshi_corner_lines(width), hoshi_corner_lines(height));
+        let (center_x, center_y) = (width / 2, height / 2);
+        let mut points = vec![];
+        for &x in &xs {
+            for &y in &ys {
+                points.push((x, y));
+            }
+        }
+        if width % 2 == 1 && width >= 15 {
+            points.extend(ys.iter().map(|&y| (center_x, y)));
+        }
+        if height % 2 == 1 && height >= 15 {
+            points.extend(xs.iter().map(|&x| (x, center_y)));
         }
+        if width % 2 == 1 && height % 2 == 1 && width.min(height) >= 11 {
+            points.push((center_x, center_y));
+        }
+
+        points
+    }
+}
+
+// Returns the lines near each end of a side which have corner star points on them, if the side
+// is long enough to leave some space between them.
+fn hoshi_corner_lines(size: u8) -> Vec<u8> {
+    let edge_distance = if size < 12 { 2 } else { 3 };
+    if size < 2 * edge_distance + 3 {
+        return vec![];
     }
+
+    vec![edge_distance, size - 1 - edge_distance]
 }
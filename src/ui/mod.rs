@@ -1,53 +1,163 @@
+mod game_picker;
 mod goban_display;
+mod recent_games;
 mod sgf_walker;
 
-use crate::goban::{Goban, Stone, StoneColor};
-use crate::sgf_parse::{go, SgfNode};
+use crate::goban::{
+    GameResult, Goban, GobanError, GobanHistory, Influence, Rules, Score, ScoringRule, Stone,
+    StoneColor, WinMargin, MAX_BOARD_SIZE,
+};
+use crate::sgf_parse::{go, Color};
+pub use game_picker::GameOrder;
 use goban_display::GobanDisplay;
-use sgf_walker::{GameState, SgfWalker};
+pub use goban_display::FONT_NAME;
+pub use recent_games::RecentGames;
+use sgf_walker::{GameState, Step};
+pub use sgf_walker::{SgfWalker, VariationPolicy};
 use std::error;
 use std::time;
 
 pub struct UI {
-    goban: Goban,
+    history: GobanHistory,
     move_delay: u64,
     end_delay: u64,
+    reverse: bool,
+    show_influence: bool,
     game_state: GameState,
     last_action_time: time::Instant,
     sgf_walker: SgfWalker,
+    komi: f64,
+    handicap: Option<u8>,
+    // Kept from the start of the game, since the walker moves on before the end is shown.
+    black_player: String,
+    white_player: String,
+    recorded_result: Option<String>,
+    recorded_black_territory: Option<Vec<(u8, u8)>>,
+    recorded_white_territory: Option<Vec<(u8, u8)>>,
+    dead_stones: Vec<(u8, u8)>,
+    score: Option<Score>,
+    result_lines: Vec<String>,
+    influence: Option<Influence>,
 }
 
 impl UI {
     pub fn new(
-        sgfs: Vec<SgfNode<go::Prop>>,
+        sgf_walker: SgfWalker,
         move_delay: u64,
         end_delay: u64,
-    ) -> Result<UI, Box<dyn error::Error>> {
-        Ok(UI {
-            goban: Goban::new((19, 19)),
+        reverse: bool,
+        show_influence: bool,
+    ) -> UI {
+        UI {
+            history: GobanHistory::new(Goban::new((19, 19))),
             move_delay,
             end_delay,
+            reverse,
+            show_influence,
             game_state: GameState::New,
             last_action_time: time::Instant::now(),
-            sgf_walker: SgfWalker::new(sgfs)?,
-        })
+            sgf_walker,
+            komi: 0.0,
+            handicap: None,
+            black_player: String::new(),
+            white_player: String::new(),
+            recorded_result: None,
+            recorded_black_territory: None,
+            recorded_white_territory: None,
+            dead_stones: vec![],
+            score: None,
+            result_lines: vec![],
+            influence: None,
+        }
     }
 
     pub fn draw(&self, frame: &mut nanovg::Frame, width: f32, height: f32) {
-        let goban_display = GobanDisplay::new(&self.goban);
+        let mut info_lines = vec![];
+        if !self.black_player.is_empty() {
+            info_lines.push(format!("Black: {}", self.black_player));
+        }
+        if !self.white_player.is_empty() {
+            info_lines.push(format!("White: {}", self.white_player));
+        }
+        if let Some(handicap) = self.handicap {
+            info_lines.push(format!("Handicap: {}", handicap));
+        }
+        let mut goban_display = GobanDisplay::new(self.history.current());
+        if let Some(influence) = &self.influence {
+            goban_display = goban_display.with_influence(influence);
+        }
+        if self.history.current_index() + 1 == self.history.len() {
+            // Prefer the territory as recorded in the game to our own count.
+            if self.recorded_black_territory.is_some() || self.recorded_white_territory.is_some() {
+                goban_display = goban_display.with_territory(
+                    self.recorded_black_territory.as_deref().unwrap_or_default(),
+                    self.recorded_white_territory.as_deref().unwrap_or_default(),
+                );
+            } else if let Some(score) = &self.score {
+                goban_display =
+                    goban_display.with_territory(&score.black_territory, &score.white_territory);
+            }
+            goban_display = goban_display.with_dead_stones(&self.dead_stones);
+            info_lines.extend(self.result_lines.iter().cloned());
+        }
+        goban_display = goban_display.with_info_lines(&info_lines);
         goban_display.draw(frame, width, height);
     }
 
     pub fn update_game_state(&mut self) -> Result<(), Box<dyn error::Error>> {
+        match self.advance() {
+            // A broken record shouldn't stop the screensaver, so just move on to another game.
+            Err(error) if is_broken_record(&*error) => {
+                eprintln!("Skipping {}: {}", self.sgf_walker.source(), error);
+                self.sgf_walker.next_game();
+                // Wait before starting the next game, so a library of broken games doesn't spin.
+                self.game_state = GameState::Ended;
+                self.last_action_time = time::Instant::now();
+                Ok(())
+            }
+            result => result,
+        }
+    }
+
+    fn advance(&mut self) -> Result<(), Box<dyn error::Error>> {
         match self.game_state {
             GameState::New => {
-                self.goban = Goban::new(self.get_board_size());
+                let mut goban = Goban::new(self.get_board_size()?);
+                goban.rules = self.get_rules();
+                self.handicap = self.get_handicap();
+                if let Some(handicap) = self.handicap {
+                    self.set_up_handicap(&mut goban, handicap)?;
+                }
+                self.history = GobanHistory::new(goban);
+                if let Some(game) = self.sgf_walker.game() {
+                    self.black_player = game.black_player.clone();
+                    self.white_player = game.white_player.clone();
+                }
+                self.komi = self.get_komi();
+                self.recorded_result = self.get_recorded_result();
+                self.recorded_black_territory = None;
+                self.recorded_white_territory = None;
+                self.dead_stones = vec![];
+                self.score = None;
+                self.result_lines = vec![];
+                if self.reverse {
+                    // Play through the whole game up front so it can be shown from the end.
+                    while let GameState::Ongoing = self.process_current_node()? {}
+                    self.history.go_to_end();
+                    self.last_action_time = std::time::Instant::now();
+                }
                 self.game_state = GameState::Ongoing;
+                self.update_influence();
             }
             GameState::Ongoing => {
                 if self.last_action_time.elapsed() > time::Duration::from_millis(self.move_delay) {
-                    self.game_state = self.process_current_node()?;
+                    self.game_state = if self.reverse {
+                        self.step_back()
+                    } else {
+                        self.process_current_node()?
+                    };
                     self.last_action_time = std::time::Instant::now();
+                    self.update_influence();
                 }
             }
             GameState::Ended => {
@@ -61,55 +171,221 @@ impl UI {
         Ok(())
     }
 
+    // Handicap stones are normally given with `AB` in the root node, but if they're missing we
+    // fall back on fixed placement. Either way white moves first unless `PL` says otherwise.
+    fn set_up_handicap(&self, goban: &mut Goban, handicap: u8) -> Result<(), GobanError> {
+        if self.sgf_walker.node().get_property("AB").is_none() {
+            match goban.place_handicap(handicap) {
+                // Without fixed placement there's no telling where the stones go, so do without.
+                Ok(()) | Err(GobanError::InvalidHandicap(_)) => {}
+                Err(error) => return Err(error),
+            }
+        }
+        goban.to_move = StoneColor::White;
+
+        Ok(())
+    }
+
+    // The estimate is only worth recomputing when the position changes.
+    fn update_influence(&mut self) {
+        self.influence = if self.show_influence {
+            Some(self.history.current().influence())
+        } else {
+            None
+        };
+    }
+
     fn process_current_node(&mut self) -> Result<GameState, Box<dyn error::Error>> {
+        let mut goban = self.history.current().clone();
         for prop in self.sgf_walker.node().properties() {
             match prop {
-                go::Prop::B(go::Move::Move(point)) => {
-                    if !self.is_tt_pass(point) {
-                        self.goban
-                            .play_stone(Stone::new(point.x, point.y, StoneColor::Black))?;
-                    }
+                go::Prop::B(go::Move::Move(point)) if !is_tt_pass(point, goban.size) => {
+                    goban.play_stone(Stone::new(point.x, point.y, StoneColor::Black))?;
                 }
-                go::Prop::W(go::Move::Move(point)) => {
-                    if !self.is_tt_pass(point) {
-                        self.goban
-                            .play_stone(Stone::new(point.x, point.y, StoneColor::White))?;
-                    }
+                go::Prop::W(go::Move::Move(point)) if !is_tt_pass(point, goban.size) => {
+                    goban.play_stone(Stone::new(point.x, point.y, StoneColor::White))?;
                 }
+                go::Prop::B(_) => goban.pass(StoneColor::Black),
+                go::Prop::W(_) => goban.pass(StoneColor::White),
                 go::Prop::AB(points) => {
                     for point in points.iter() {
-                        self.goban
-                            .add_stone(Stone::new(point.x, point.y, StoneColor::Black))?;
+                        goban.add_stone(Stone::new(point.x, point.y, StoneColor::Black))?;
                     }
                 }
                 go::Prop::AW(points) => {
                     for point in points.iter() {
-                        self.goban
-                            .add_stone(Stone::new(point.x, point.y, StoneColor::White))?;
+                        goban.add_stone(Stone::new(point.x, point.y, StoneColor::White))?;
                     }
                 }
                 go::Prop::AE(points) => {
                     for point in points.iter() {
-                        self.goban.clear_point((point.x, point.y));
+                        goban.clear_point((point.x, point.y));
                     }
                 }
-                go::Prop::MN(num) => self.goban.set_move_number(*num as u64),
+                go::Prop::PL(color) => {
+                    goban.to_move = match color {
+                        Color::Black => StoneColor::Black,
+                        Color::White => StoneColor::White,
+                    }
+                }
+                go::Prop::MN(num) => goban.set_move_number(*num as u64),
+                go::Prop::TB(points) => {
+                    self.recorded_black_territory =
+                        Some(points.iter().map(|point| (point.x, point.y)).collect());
+                }
+                go::Prop::TW(points) => {
+                    self.recorded_white_territory =
+                        Some(points.iter().map(|point| (point.x, point.y)).collect());
+                }
                 _ => {}
             }
         }
+        self.history.push(goban);
+
+        let game_state = match self.sgf_walker.next_node() {
+            Step::Child => GameState::Ongoing,
+            Step::Variation(depth) => {
+                // Back up to the position after the branching node. The root is at index 1.
+                self.history.go_to(depth + 1);
+                self.recorded_black_territory = None;
+                self.recorded_white_territory = None;
+                GameState::Ongoing
+            }
+            Step::NextGame => GameState::Ended,
+        };
+        if let GameState::Ended = game_state {
+            self.dead_stones = self.find_dead_stones();
+            self.score = self.score_game();
+            self.result_lines = self.get_result_lines();
+        }
+
+        Ok(game_state)
+    }
+
+    // Dead stones are those in the opponent's recorded territory. Without a record, they're
+    // estimated, unless the game was decided some way other than counting.
+    fn find_dead_stones(&self) -> Vec<(u8, u8)> {
+        let goban = self.history.current();
+        match (
+            &self.recorded_black_territory,
+            &self.recorded_white_territory,
+        ) {
+            (None, None) if self.was_decided_without_counting() => vec![],
+            (None, None) => goban.estimate_dead_stones(),
+            (black_territory, white_territory) => goban.dead_stones_in_territory(
+                black_territory.as_deref().unwrap_or_default(),
+                white_territory.as_deref().unwrap_or_default(),
+            ),
+        }
+    }
+
+    // Scores the final position, unless the game was decided some other way.
+    fn score_game(&self) -> Option<Score> {
+        if self.was_decided_without_counting() {
+            return None;
+        }
+        let goban = self.history.current();
+
+        Some(goban.score(&self.dead_stones, self.komi, goban.rules.scoring_rule))
+    }
+
+    fn was_decided_without_counting(&self) -> bool {
+        matches!(
+            self.parsed_recorded_result(),
+            Some(GameResult::Win(
+                _,
+                WinMargin::Resignation | WinMargin::Time | WinMargin::Forfeit,
+            ))
+        )
+    }
+
+    fn get_result_lines(&self) -> Vec<String> {
+        let mut lines = vec![];
+        if let Some(score) = &self.score {
+            let scoring = match score.scoring_rule {
+                ScoringRule::Area => "area",
+                ScoringRule::Territory => "territory",
+            };
+            lines.push(format!("{} by {} scoring", score.result(), scoring));
+        }
+        if let Some(recorded_result) = &self.recorded_result {
+            let differs = match (&self.score, self.parsed_recorded_result()) {
+                (Some(score), Some(result)) => !score.agrees_with(&result),
+                _ => false,
+            };
+            if differs {
+                lines.push(format!("Recorded result: {} (differs)", recorded_result));
+            } else {
+                lines.push(format!("Recorded result: {}", recorded_result));
+            }
+        }
 
-        Ok(self.sgf_walker.next_node())
+        lines
     }
 
-    fn is_tt_pass(&self, point: &go::Point) -> bool {
-        point.x == 19 && point.y == 19 && self.goban.size.0 < 20 && self.goban.size.1 < 20
+    fn parsed_recorded_result(&self) -> Option<GameResult> {
+        self.recorded_result.as_deref().and_then(GameResult::parse)
     }
 
-    fn get_board_size(&self) -> (u8, u8) {
-        match self.sgf_walker.node().get_property("SZ") {
+    fn step_back(&mut self) -> GameState {
+        if self.history.step_back() {
+            GameState::Ongoing
+        } else {
+            GameState::Ended
+        }
+    }
+
+    fn get_board_size(&self) -> Result<(u8, u8), GobanError> {
+        let size = match self.sgf_walker.node().get_property("SZ") {
             Some(go::Prop::SZ(size)) => *size,
             None => (19, 19),
             _ => unreachable!(),
+        };
+        if size.0 == 0 || size.1 == 0 || size.0 > MAX_BOARD_SIZE || size.1 > MAX_BOARD_SIZE {
+            return Err(GobanError::InvalidBoardSize(size.0, size.1));
+        }
+
+        Ok(size)
+    }
+
+    fn get_komi(&self) -> f64 {
+        match self.sgf_walker.node().get_property("KM") {
+            Some(go::Prop::KM(komi)) => *komi,
+            _ => 0.0,
         }
     }
+
+    fn get_handicap(&self) -> Option<u8> {
+        match self.sgf_walker.node().get_property("HA") {
+            // A handicap of one stone is just an even game where black takes no komi.
+            Some(go::Prop::HA(handicap)) if *handicap >= 2 => {
+                std::convert::TryFrom::try_from(*handicap).ok()
+            }
+            _ => None,
+        }
+    }
+
+    fn get_recorded_result(&self) -> Option<String> {
+        match self.sgf_walker.node().get_property("RE") {
+            Some(go::Prop::RE(text)) => Some(text.text.clone()),
+            _ => None,
+        }
+    }
+
+    fn get_rules(&self) -> Rules {
+        match self.sgf_walker.node().get_property("RU") {
+            Some(go::Prop::RU(text)) => Rules::from_name(&text.text),
+            _ => Rules::default(),
+        }
+    }
+}
+
+// Whether an error means the game record itself is wrong. Board errors all come from something
+// the record asks for, like a suicide the rules don't allow or a move on an occupied point.
+fn is_broken_record(error: &(dyn error::Error + 'static)) -> bool {
+    error.is::<GobanError>()
+}
+
+fn is_tt_pass(point: &go::Point, board_size: (u8, u8)) -> bool {
+    point.x == 19 && point.y == 19 && board_size.0 < 20 && board_size.1 < 20
 }
@@ -0,0 +1,105 @@
+use std::io::Write;
+use std::path::PathBuf;
+
+use crate::xdg;
+
+/// Games shown recently, kept in a state file so they can be avoided across runs.
+///
+/// Several instances may run at once (one per screen), so the file is only ever appended to with
+/// a single write per game, and rewritten by atomically renaming a new file over it. At worst an
+/// instance compacting the file at the same moment another appends to it will drop one entry.
+pub struct RecentGames {
+    path: Option<PathBuf>,
+    ids: Vec<String>,
+    limit: usize,
+}
+
+impl RecentGames {
+    /// Loads the most recent `limit` games from the file at `path`.
+    pub fn load(path: PathBuf, limit: usize) -> RecentGames {
+        if limit == 0 {
+            return RecentGames::disabled();
+        }
+        let ids = match std::fs::read_to_string(&path) {
+            Ok(contents) => last_lines(&contents, limit),
+            Err(_) => vec![],
+        };
+
+        RecentGames {
+            path: Some(path),
+            ids,
+            limit,
+        }
+    }
+
+    /// Returns an empty history which isn't saved anywhere.
+    pub fn disabled() -> RecentGames {
+        RecentGames {
+            path: None,
+            ids: vec![],
+            limit: 0,
+        }
+    }
+
+    /// Returns the path of the state file under `$XDG_STATE_HOME`.
+    pub fn default_path() -> PathBuf {
+        let mut path = xdg::base_dir("XDG_STATE_HOME", ".local/state");
+        path.push("goban-screenhack");
+        path.push("recent");
+
+        path
+    }
+
+    pub fn contains(&self, id: &str) -> bool {
+        self.ids.iter().any(|recent| recent == id)
+    }
+
+    /// Returns the most recently shown game.
+    pub fn last(&self) -> Option<&str> {
+        self.ids.last().map(String::as_str)
+    }
+
+    pub fn record(&mut self, id: &str) -> std::io::Result<()> {
+        let path = match &self.path {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+        self.ids.retain(|recent| recent != id);
+        self.ids.push(id.to_string());
+        if self.ids.len() > self.limit {
+            self.ids.remove(0);
+        }
+
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)?;
+        file.write_all(format!("{}\n", id).as_bytes())?;
+
+        // Keep the file from growing forever.
+        let contents = std::fs::read_to_string(path)?;
+        if contents.lines().count() > 4 * self.limit {
+            let temp_path = path.with_extension(format!("{}.tmp", std::process::id()));
+            let mut lines = last_lines(&contents, self.limit).join("\n");
+            lines.push('\n');
+            std::fs::write(&temp_path, lines)?;
+            std::fs::rename(&temp_path, path)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn last_lines(contents: &str, count: usize) -> Vec<String> {
+    let lines: Vec<_> = contents
+        .lines()
+        .filter(|line| !line.is_empty())
+        .map(String::from)
+        .collect();
+    let start = lines.len().saturating_sub(count);
+
+    lines[start..].to_vec()
+}
@@ -1,45 +1,285 @@
+use super::game_picker::{GameOrder, GamePicker};
+use super::recent_games::RecentGames;
+use crate::catalog::CatalogEntry;
+use crate::goban::MAX_BOARD_SIZE;
 use crate::sgf_parse::{go, SgfNode};
-use rand::seq::SliceRandom;
-use rand::thread_rng;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 use std::error;
 use std::ptr;
+use std::sync::mpsc;
+use std::time;
+
+// How long to let games arrive before picking the first, so that it isn't always from the first
+// file found. Cached files arrive quickly, so this is usually most of the library.
+const STARTUP_WAIT: time::Duration = time::Duration::from_millis(500);
 
 // Self referential struct using a raw pointer to keep track of the current node.
 pub struct SgfWalker {
-    sgfs: std::pin::Pin<Vec<SgfNode<go::Prop>>>,
+    games: GamePicker,
+    // Games still being indexed arrive here. Dropped once indexing is finished.
+    new_games: Option<mpsc::Receiver<Vec<CatalogEntry>>>,
+    // Only the game being shown is kept parsed.
+    game: Option<CatalogEntry>,
+    root: std::pin::Pin<Box<SgfNode<go::Prop>>>,
     node_ptr: ptr::NonNull<SgfNode<go::Prop>>,
+    // Index of the child taken at each node on the way from the root to the current node.
+    path: Vec<usize>,
+    // Number of nodes with more than one child passed on the way to the current node.
+    forks_passed: usize,
+    variation_policy: VariationPolicy,
+    rng: StdRng,
+    recent_games: RecentGames,
+}
+
+/// How to pick a line through games with variations.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum VariationPolicy {
+    /// Only play the main line.
+    MainLine,
+    /// Play the main line, then every variation in depth first order.
+    All,
+    /// Pick a random child at every fork.
+    Random,
+    /// Play the line with the most moves.
+    Longest,
+    /// Pick the child with the given index at each successive fork, and the first child once
+    /// the path runs out.
+    Path(Vec<usize>),
+    /// Follow children marked as the main line by the application which wrote the file.
+    Marked,
 }
 
 impl SgfWalker {
-    pub fn new(sgfs: Vec<SgfNode<go::Prop>>) -> Result<SgfWalker, SgfWalkerError> {
-        if sgfs.is_empty() {
-            return Err(SgfWalkerError::NoSgfs);
+    /// Creates a walker playing games as they arrive from `new_games`.
+    ///
+    /// Unless there's a seed, this waits for up to `STARTUP_WAIT`, or longer if no game has loaded
+    /// by then, and games indexed after that join the pool as they arrive. With a seed, it waits
+    /// for every game first, so that the random choices don't depend on how quickly games were
+    /// indexed. It's an error if the channel closes before any game could be loaded.
+    pub fn new(
+        new_games: mpsc::Receiver<Vec<CatalogEntry>>,
+        variation_policy: VariationPolicy,
+        game_order: GameOrder,
+        seed: Option<u64>,
+        recent_games: RecentGames,
+    ) -> Result<SgfWalker, SgfWalkerError> {
+        let root = Box::pin(SgfNode::default());
+        let node_ptr = ptr::NonNull::from(&*root);
+        let mut sgf_walker = SgfWalker {
+            games: GamePicker::new(game_order, &recent_games),
+            new_games: None,
+            game: None,
+            root,
+            node_ptr,
+            path: vec![],
+            forks_passed: 0,
+            variation_policy,
+            rng: match seed {
+                Some(seed) => StdRng::seed_from_u64(seed),
+                None => StdRng::from_entropy(),
+            },
+            recent_games,
+        };
+        if seed.is_some() {
+            for games in new_games.iter() {
+                sgf_walker.add_games(games);
+            }
+        } else {
+            let deadline = time::Instant::now() + STARTUP_WAIT;
+            while let Ok(games) =
+                new_games.recv_timeout(deadline.saturating_duration_since(time::Instant::now()))
+            {
+                sgf_walker.add_games(games);
+            }
+        }
+        while !sgf_walker.start_next_game() {
+            match new_games.recv() {
+                Ok(games) => sgf_walker.add_games(games),
+                Err(_) => return Err(SgfWalkerError::NoSgfs),
+            }
         }
-        let sgfs = std::pin::Pin::new(sgfs);
-        let mut rng = thread_rng();
-        let node_ptr = ptr::NonNull::from(sgfs.choose(&mut rng).unwrap()); // sgfs is never empty
+        sgf_walker.new_games = Some(new_games);
 
-        Ok(SgfWalker { sgfs, node_ptr })
+        Ok(sgf_walker)
     }
 
     pub fn node(&self) -> &SgfNode<go::Prop> {
         unsafe { self.node_ptr.as_ref() }
     }
 
-    pub fn next_node(&mut self) -> GameState {
-        let mut game_state = GameState::Ongoing;
-        let next_node = match self.node().children().next() {
-            None => {
-                game_state = GameState::Ended;
-                let mut rng = thread_rng();
-                self.sgfs.choose(&mut rng).unwrap() // sgfs is never empty
+    /// The catalog entry for the game being shown.
+    pub fn game(&self) -> Option<&CatalogEntry> {
+        self.game.as_ref()
+    }
+
+    /// Identifies the game being shown.
+    pub fn source(&self) -> &str {
+        self.game.as_ref().map_or("", |game| &game.source)
+    }
+
+    pub fn next_node(&mut self) -> Step {
+        // The node is owned by `root`, so it can be borrowed alongside the RNG.
+        let node = unsafe { self.node_ptr.as_ref() };
+        let choice = choose_child(
+            node,
+            &self.variation_policy,
+            self.forks_passed,
+            &mut self.rng,
+        );
+        if let Some(index) = choice {
+            if node.children.len() > 1 {
+                self.forks_passed += 1;
             }
-            Some(node) => node,
-        };
-        self.node_ptr = ptr::NonNull::from(next_node);
+            self.node_ptr = ptr::NonNull::from(&node.children[index]);
+            self.path.push(index);
+            return Step::Child;
+        }
+        if let VariationPolicy::All = self.variation_policy {
+            if let Some(depth) = self.next_variation() {
+                return Step::Variation(depth);
+            }
+        }
+        self.next_game();
+
+        Step::NextGame
+    }
+
+    /// Moves on to the root of the next game.
+    pub fn next_game(&mut self) {
+        if !self.start_next_game() {
+            // Nothing else could be loaded, so show the same game again.
+            self.node_ptr = ptr::NonNull::from(&*self.root);
+            self.path.clear();
+            self.forks_passed = 0;
+        }
+    }
+
+    // Adds any games indexed since the last call.
+    fn receive_games(&mut self) {
+        let mut received = vec![];
+        if let Some(new_games) = &self.new_games {
+            loop {
+                match new_games.try_recv() {
+                    Ok(games) => received.extend(games),
+                    Err(mpsc::TryRecvError::Empty) => break,
+                    Err(mpsc::TryRecvError::Disconnected) => {
+                        self.new_games = None;
+                        break;
+                    }
+                }
+            }
+        }
+        self.add_games(received);
+    }
+
+    fn add_games(&mut self, games: Vec<CatalogEntry>) {
+        for game in games {
+            self.games.add_game(game, &mut self.rng);
+        }
+    }
+
+    // Loads the next game to be shown, skipping any which fail to load. Returns false if every
+    // game failed.
+    fn start_next_game(&mut self) -> bool {
+        self.receive_games();
+        for _ in 0..self.games.len() {
+            let game = self.games.next_game(&self.recent_games, &mut self.rng);
+            // The catalog already knows the board size, so there's no need to load the game.
+            let (width, height) = game.board_size;
+            if width == 0 || height == 0 || width > MAX_BOARD_SIZE || height > MAX_BOARD_SIZE {
+                eprintln!(
+                    "Skipping {}: unsupported {}x{} board",
+                    game.source, width, height
+                );
+                continue;
+            }
+            let root = match game.load() {
+                Ok(root) => root,
+                Err(error) => {
+                    eprintln!("Error loading {}: {}", game.source, error);
+                    continue;
+                }
+            };
+            if let Err(error) = self.recent_games.record(&game.source) {
+                eprintln!("Failed to save recently shown games: {}", error);
+            }
+            self.game = Some(game.clone());
+            self.root = Box::pin(root);
+            self.node_ptr = ptr::NonNull::from(&*self.root);
+            self.path.clear();
+            self.forks_passed = 0;
+            return true;
+        }
 
-        game_state
+        false
     }
+
+    // Backs up to the nearest node with an unplayed variation, and moves on to the start of that
+    // variation. Returns the depth of the node it branches from.
+    fn next_variation(&mut self) -> Option<usize> {
+        while let Some(index) = self.path.pop() {
+            let mut parent: &SgfNode<go::Prop> = &self.root;
+            for &i in &self.path {
+                parent = &parent.children[i];
+            }
+            if let Some(next_node) = parent.children.get(index + 1) {
+                self.node_ptr = ptr::NonNull::from(next_node);
+                self.path.push(index + 1);
+                return Some(self.path.len() - 1);
+            }
+        }
+
+        None
+    }
+}
+
+// Returns the index of the child of `node` to play next, if there are any. `forks_passed` is the
+// number of nodes with more than one child passed on the way to `node`.
+fn choose_child(
+    node: &SgfNode<go::Prop>,
+    variation_policy: &VariationPolicy,
+    forks_passed: usize,
+    rng: &mut StdRng,
+) -> Option<usize> {
+    let children = &node.children;
+    if children.is_empty() {
+        return None;
+    }
+    let index = match variation_policy {
+        VariationPolicy::MainLine | VariationPolicy::All => 0,
+        VariationPolicy::Random => rng.gen_range(0..children.len()),
+        VariationPolicy::Longest => (0..children.len())
+            .rev()
+            .max_by_key(|&i| line_length(&children[i]))
+            .unwrap_or(0),
+        VariationPolicy::Path(path) => path
+            .get(forks_passed)
+            .copied()
+            .filter(|&i| i < children.len() && children.len() > 1)
+            .unwrap_or(0),
+        VariationPolicy::Marked => children.iter().position(is_marked_main).unwrap_or(0),
+    };
+
+    Some(index)
+}
+
+// Number of nodes in the longest line starting at the node.
+fn line_length(node: &SgfNode<go::Prop>) -> usize {
+    1 + node.children.iter().map(line_length).max().unwrap_or(0)
+}
+
+// There's no standard way to mark a variation as the main line. Some editors name the node,
+// and others write their own `MAIN` property.
+fn is_marked_main(node: &SgfNode<go::Prop>) -> bool {
+    node.properties().any(|prop| match prop {
+        go::Prop::N(name) => {
+            let name = name.text.trim().to_lowercase();
+            name == "main" || name == "main line" || name == "mainline"
+        }
+        go::Prop::Unknown(identifier, _) => identifier == "MAIN",
+        _ => false,
+    })
 }
 
 pub enum GameState {
@@ -48,6 +288,15 @@ pub enum GameState {
     Ended,
 }
 
+/// Where `SgfWalker::next_node` moved to.
+pub enum Step {
+    Child,
+    /// The start of a variation branching from the node at the given depth.
+    Variation(usize),
+    /// The root of the next game.
+    NextGame,
+}
+
 #[derive(Debug)]
 pub enum SgfWalkerError {
     NoSgfs,
@@ -62,3 +311,57 @@ impl std::fmt::Display for SgfWalkerError {
 }
 
 impl error::Error for SgfWalkerError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The root has three children: a one move line, a three move line marked as the main line,
+    // and a two move line.
+    const GAME: &str = "(;GM[1](;B[aa])(;N[Main]B[bb];W[cc];B[dd])(;B[ee];W[ff]))";
+
+    fn root() -> SgfNode<go::Prop> {
+        crate::large_boards::parse(GAME).unwrap().remove(0)
+    }
+
+    fn choose(variation_policy: VariationPolicy, forks_passed: usize) -> Option<usize> {
+        let mut rng = StdRng::seed_from_u64(0);
+        choose_child(&root(), &variation_policy, forks_passed, &mut rng)
+    }
+
+    #[test]
+    fn choose_child_follows_policy() {
+        assert_eq!(choose(VariationPolicy::MainLine, 0), Some(0));
+        assert_eq!(choose(VariationPolicy::All, 0), Some(0));
+        assert_eq!(choose(VariationPolicy::Longest, 0), Some(1));
+        assert_eq!(choose(VariationPolicy::Marked, 0), Some(1));
+        assert_eq!(choose(VariationPolicy::Path(vec![2]), 0), Some(2));
+        // Out of range choices, and forks past the end of the path, take the first child.
+        assert_eq!(choose(VariationPolicy::Path(vec![5]), 0), Some(0));
+        assert_eq!(choose(VariationPolicy::Path(vec![2]), 1), Some(0));
+    }
+
+    #[test]
+    fn choose_child_stops_at_leaves() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let leaf = &root().children[0];
+        assert_eq!(
+            choose_child(leaf, &VariationPolicy::Random, 0, &mut rng),
+            None
+        );
+    }
+
+    #[test]
+    fn random_choices_depend_only_on_seed() {
+        let root = root();
+        let choices = |seed| {
+            let mut rng = StdRng::seed_from_u64(seed);
+            (0..20)
+                .map(|_| choose_child(&root, &VariationPolicy::Random, 0, &mut rng).unwrap())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(choices(4), choices(4));
+        assert!(choices(4).iter().all(|&i| i < 3));
+        assert_ne!(choices(4), choices(5));
+    }
+}
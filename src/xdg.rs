@@ -0,0 +1,16 @@
+//! Locations from the XDG base directory specification.
+
+use std::path::PathBuf;
+
+/// Returns the directory named by an XDG environment variable, or its default under `$HOME`.
+pub fn base_dir(variable: &str, default: &str) -> PathBuf {
+    match std::env::var(variable).ok().map(PathBuf::from) {
+        Some(path) => path,
+        None => {
+            let mut path = PathBuf::from(std::env::var("HOME").unwrap_or_else(|_| "".to_string()));
+            path.push(default);
+
+            path
+        }
+    }
+}